@@ -12,6 +12,8 @@ export const INITIAL_MIN_ACCOUNT_SIZE =
   DidAccountSizeHelper.getTotalInitialNativeAccountSize();
 //export const INITIAL_DEFAULT_ACCOUNT_SIZE = 10_000;
 export const DEFAULT_SEED_STRING = 'did-account';
+// domain separator of version 1 signed update messages
+export const SIGNED_UPDATE_DOMAIN_V1 = 'did:sol:update:v1';
 
 export const VALID_DID_REGEX = /^did:([a-z\d:]*):([a-zA-z\d]+)$/;
 
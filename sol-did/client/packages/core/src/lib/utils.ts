@@ -22,6 +22,7 @@ import {
   DID_SOL_PREFIX,
   DID_SOL_PROGRAM,
   LEGACY_DID_SOL_PROGRAM,
+  SIGNED_UPDATE_DOMAIN_V1,
   VALID_DID_REGEX,
 } from './const';
 import {
@@ -53,14 +54,31 @@ export const findLegacyProgramAddress = (authority: PublicKey) =>
     LEGACY_DID_SOL_PROGRAM
   );
 
+const CLOSE_DISCRIMINATOR = Buffer.from(
+  anchor.utils.sha256.hash('global:close'),
+  'hex'
+).subarray(0, 8);
+
 export const ethSignPayload = async (
   instruction: TransactionInstruction,
   nonce: anchor.BN,
   signer: EthSigner
 ): Promise<TransactionInstruction> => {
-  // Anchor 8 bytes prefix, Option<T> byte suffix
+  // Anchor 8 bytes discriminator prefix, Option<T> byte suffix
+  const discriminator = instruction.data.subarray(0, 8);
+  const payload = discriminator.equals(CLOSE_DISCRIMINATOR)
+    ? instruction.keys[2].pubkey.toBuffer() // rent destination
+    : instruction.data.subarray(8, -1);
   const nonceBytes = Buffer.from(nonce.toArray('le', 8));
-  const message = Buffer.concat([instruction.data.subarray(8, -1), nonceBytes]);
+  // version 1 message, bound to the program, the DID account and the instruction
+  const message = Buffer.concat([
+    Buffer.from(SIGNED_UPDATE_DOMAIN_V1),
+    instruction.programId.toBuffer(),
+    instruction.keys[0].pubkey.toBuffer(),
+    discriminator,
+    payload,
+    nonceBytes,
+  ]);
 
   const signatureFull = await signer.signMessage(message);
   // add signature to payload
@@ -1,4 +1,17 @@
+use solana_program::{pubkey, pubkey::Pubkey};
+
 pub const DID_ACCOUNT_SEED: &str = "did-account";
+pub const DID_RECOVERY_SEED: &str = "did-recovery";
+/// Seconds after the challenge period in which a recovery can be executed. Afterwards it is stale
+/// and anyone can close it.
+pub const RECOVERY_EXECUTION_PERIOD: i64 = 30 * 24 * 60 * 60;
 pub const DID_PREFIX: &str = "did:";
 pub const DID_SOL_PREFIX: &str = "did:sol:";
 pub const VM_DEFAULT_FRAGMENT_NAME: &str = "default";
+/// Layout version of newly created (or upgraded) DidAccounts. Every layout change gets a new
+/// version, previous layouts are kept in `legacy` to read and upgrade existing accounts.
+pub const DID_ACCOUNT_VERSION: u8 = 4;
+/// Native secp256r1 signature verification program (SIMD-0075).
+pub const SECP256R1_PROGRAM_ID: Pubkey = pubkey!("Secp256r1SigVerify1111111111111111111111111");
+/// Domain separator of version 1 signed update messages, see `utils::signed_update_message`.
+pub const SIGNED_UPDATE_DOMAIN_V1: &[u8] = b"did:sol:update:v1";
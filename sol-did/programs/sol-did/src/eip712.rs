@@ -0,0 +1,636 @@
+//! EIP-712 typed structured data of DID updates.
+//!
+//! Ethereum authorities can sign an update as EIP-712 typed data instead of a personal message,
+//! so that wallets can display what they approve. Every typed struct starts with the DID
+//! (`did:sol:<identifier>`) and the nonce of the DID account, followed by the instruction
+//! arguments. Optional values are encoded as arrays with zero or one element.
+use crate::instructions::BatchOperation;
+use crate::state::{RecoveryPolicy, Service, ServiceEndpoint, ServiceProperty, VerificationMethod};
+use anchor_lang::prelude::Pubkey;
+use solana_program::keccak::{hash, hashv};
+
+pub const EIP712_DOMAIN_NAME: &str = "did:sol";
+pub const EIP712_DOMAIN_VERSION: &str = "1";
+/// Wallets only sign typed data for the chain they are connected to, did:sol uses Ethereum mainnet.
+pub const EIP712_DOMAIN_CHAIN_ID: u64 = 1;
+
+const EIP712_DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,bytes32 salt)";
+const BATCH_OPERATION_TYPE: &str = "BatchOperation(string operation,string fragment,VerificationMethod[] verificationMethod,uint16[] flags,Service[] service,bool[] allowOverwrite,string[] nativeControllers,string[] otherControllers,string[][] serviceTypes,uint8[] endpointKind,string[] serviceEndpoint)";
+const RECOVERY_POLICY_TYPE: &str =
+    "RecoveryPolicy(string[] guardians,uint8 threshold,int64 challengePeriod)";
+const SERVICE_TYPE: &str = "Service(string fragment,string[] serviceTypes,uint8 endpointKind,string serviceEndpoint,ServiceProperty[] properties)";
+const SERVICE_PROPERTY_TYPE: &str = "ServiceProperty(string name,string value)";
+const VERIFICATION_METHOD_TYPE: &str = "VerificationMethod(string fragment,uint16 flags,uint8 methodType,bytes keyData,int64[] validFrom,int64[] validUntil,uint32[] permissions)";
+
+/// The EIP-712 struct of an instruction.
+pub struct Eip712Struct {
+    /// Name of the struct
+    pub name: &'static str,
+    /// Members of the struct after `string did,uint64 nonce`, e.g. `string fragment`
+    pub members: &'static str,
+    /// Definitions of the referenced struct types, sorted by name
+    pub referenced_types: &'static [&'static str],
+    /// Encoded values of the members
+    pub values: Vec<[u8; 32]>,
+}
+
+impl Eip712Struct {
+    /// encodeType of the struct
+    pub fn encode_type(&self) -> String {
+        format!(
+            "{}(string did,uint64 nonce,{}){}",
+            self.name,
+            self.members,
+            self.referenced_types.concat()
+        )
+    }
+
+    /// The hash that is signed for the DID `did` with `nonce`:
+    /// keccak256("\x19\x01" || domainSeparator || hashStruct(message))
+    pub fn signing_hash(&self, did: &str, nonce: u64) -> [u8; 32] {
+        let values = [
+            vec![encode_string(did), encode_uint(nonce)],
+            self.values.clone(),
+        ]
+        .concat();
+        let struct_hash = hash_struct(&self.encode_type(), &values);
+
+        encode_typed_data(&domain_separator(), &struct_hash)
+    }
+}
+
+/// The salt of the domain is the program id, so that signatures are bound to this program.
+pub fn domain_separator() -> [u8; 32] {
+    hash_struct(
+        EIP712_DOMAIN_TYPE,
+        &[
+            encode_string(EIP712_DOMAIN_NAME),
+            encode_string(EIP712_DOMAIN_VERSION),
+            encode_uint(EIP712_DOMAIN_CHAIN_ID),
+            crate::ID.to_bytes(),
+        ],
+    )
+}
+
+fn encode_typed_data(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    hashv(&[b"\x19\x01", domain_separator, struct_hash]).to_bytes()
+}
+
+fn hash_struct(encoded_type: &str, values: &[[u8; 32]]) -> [u8; 32] {
+    let type_hash = hash(encoded_type.as_bytes()).to_bytes();
+    let mut data = vec![type_hash.as_ref()];
+    data.extend(values.iter().map(|value| value.as_ref()));
+    hashv(&data).to_bytes()
+}
+
+fn encode_string(value: &str) -> [u8; 32] {
+    hash(value.as_bytes()).to_bytes()
+}
+
+fn encode_bytes(value: &[u8]) -> [u8; 32] {
+    hash(value).to_bytes()
+}
+
+fn encode_uint(value: u64) -> [u8; 32] {
+    let mut encoded = [0u8; 32];
+    encoded[24..].copy_from_slice(&value.to_be_bytes());
+    encoded
+}
+
+fn encode_int(value: i64) -> [u8; 32] {
+    // two's complement, sign extended to 256 bits
+    let mut encoded = if value < 0 { [0xff; 32] } else { [0u8; 32] };
+    encoded[24..].copy_from_slice(&value.to_be_bytes());
+    encoded
+}
+
+fn encode_bool(value: bool) -> [u8; 32] {
+    encode_uint(u64::from(value))
+}
+
+fn encode_array(values: impl IntoIterator<Item = [u8; 32]>) -> [u8; 32] {
+    let values: Vec<[u8; 32]> = values.into_iter().collect();
+    hashv(
+        &values
+            .iter()
+            .map(|value| value.as_ref())
+            .collect::<Vec<_>>(),
+    )
+    .to_bytes()
+}
+
+fn encode_controllers(controllers: &[Pubkey]) -> [u8; 32] {
+    encode_array(
+        controllers
+            .iter()
+            .map(|controller| encode_string(&controller.to_string())),
+    )
+}
+
+fn encode_strings(values: &[String]) -> [u8; 32] {
+    encode_array(values.iter().map(|value| encode_string(value)))
+}
+
+fn hash_verification_method(vm: &VerificationMethod) -> [u8; 32] {
+    hash_struct(
+        VERIFICATION_METHOD_TYPE,
+        &[
+            encode_string(&vm.fragment),
+            encode_uint(vm.flags.into()),
+            encode_uint(vm.method_type.into()),
+            encode_bytes(&vm.key_data),
+            encode_array(vm.valid_from.map(encode_int)),
+            encode_array(vm.valid_until.map(encode_int)),
+            encode_array(
+                vm.permissions
+                    .map(|permissions| encode_uint(permissions.into())),
+            ),
+        ],
+    )
+}
+
+fn hash_service(service: &Service) -> [u8; 32] {
+    let (endpoint_kind, endpoint) = encode_service_endpoint(&service.service_endpoint);
+    hash_struct(
+        &[SERVICE_TYPE, SERVICE_PROPERTY_TYPE].concat(),
+        &[
+            encode_string(&service.fragment),
+            encode_strings(&service.service_types),
+            encode_uint(endpoint_kind),
+            encode_string(endpoint),
+            encode_array(service.properties.iter().map(hash_service_property)),
+        ],
+    )
+}
+
+/// The kind (0: URI, 1: JSON) and the value of a service endpoint
+fn encode_service_endpoint(service_endpoint: &ServiceEndpoint) -> (u64, &str) {
+    match service_endpoint {
+        ServiceEndpoint::Uri(uri) => (0, uri),
+        ServiceEndpoint::Json(json) => (1, json),
+    }
+}
+
+fn hash_service_property(property: &ServiceProperty) -> [u8; 32] {
+    hash_struct(
+        SERVICE_PROPERTY_TYPE,
+        &[
+            encode_string(&property.name),
+            encode_string(&property.value),
+        ],
+    )
+}
+
+fn hash_recovery_policy(recovery_policy: &RecoveryPolicy) -> [u8; 32] {
+    hash_struct(
+        RECOVERY_POLICY_TYPE,
+        &[
+            encode_controllers(&recovery_policy.guardians),
+            encode_uint(recovery_policy.threshold.into()),
+            encode_int(recovery_policy.challenge_period),
+        ],
+    )
+}
+
+/// Batch operations share one struct, members that are not an argument of the operation are
+/// empty. `operation` is the name of the `BatchOperation` variant.
+fn hash_batch_operation(operation: &BatchOperation) -> [u8; 32] {
+    let mut fragment = "";
+    let mut verification_method = None;
+    let mut flags = None;
+    let mut service = None;
+    let mut allow_overwrite = None;
+    let (mut native_controllers, mut other_controllers): (&[Pubkey], &[String]) = (&[], &[]);
+    let mut service_types = None;
+    let mut service_endpoint = None;
+    let name = match operation {
+        BatchOperation::AddVerificationMethod {
+            verification_method: vm,
+        } => {
+            verification_method = Some(vm);
+            "AddVerificationMethod"
+        }
+        BatchOperation::RemoveVerificationMethod { fragment: vm } => {
+            fragment = vm;
+            "RemoveVerificationMethod"
+        }
+        BatchOperation::SetVmFlags { flags_vm } => {
+            fragment = &flags_vm.fragment;
+            flags = Some(flags_vm.flags);
+            "SetVmFlags"
+        }
+        BatchOperation::AddService {
+            service: added_service,
+            allow_overwrite: overwrite,
+        } => {
+            service = Some(added_service);
+            allow_overwrite = Some(*overwrite);
+            "AddService"
+        }
+        BatchOperation::RemoveService {
+            fragment: removed_service,
+        } => {
+            fragment = removed_service;
+            "RemoveService"
+        }
+        BatchOperation::SetControllers {
+            set_controllers_arg,
+        } => {
+            native_controllers = &set_controllers_arg.native_controllers;
+            other_controllers = &set_controllers_arg.other_controllers;
+            "SetControllers"
+        }
+        BatchOperation::UpdateService { update_service_arg } => {
+            fragment = &update_service_arg.fragment;
+            service_types = update_service_arg.service_types.as_deref();
+            service_endpoint = update_service_arg.service_endpoint.as_ref();
+            "UpdateService"
+        }
+    };
+    let (endpoint_kinds, endpoints): (Vec<_>, Vec<_>) = service_endpoint
+        .map(encode_service_endpoint)
+        .into_iter()
+        .map(|(kind, endpoint)| (encode_uint(kind), encode_string(endpoint)))
+        .unzip();
+
+    hash_struct(
+        &[
+            BATCH_OPERATION_TYPE,
+            SERVICE_TYPE,
+            SERVICE_PROPERTY_TYPE,
+            VERIFICATION_METHOD_TYPE,
+        ]
+        .concat(),
+        &[
+            encode_string(name),
+            encode_string(fragment),
+            encode_array(verification_method.map(hash_verification_method)),
+            encode_array(flags.map(|flags| encode_uint(flags.into()))),
+            encode_array(service.map(hash_service)),
+            encode_array(allow_overwrite.map(encode_bool)),
+            encode_controllers(native_controllers),
+            encode_strings(other_controllers),
+            encode_array(service_types.map(encode_strings)),
+            encode_array(endpoint_kinds),
+            encode_array(endpoints),
+        ],
+    )
+}
+
+pub fn add_verification_method(verification_method: &VerificationMethod) -> Eip712Struct {
+    Eip712Struct {
+        name: "AddVerificationMethod",
+        members: "VerificationMethod verificationMethod",
+        referenced_types: &[VERIFICATION_METHOD_TYPE],
+        values: vec![hash_verification_method(verification_method)],
+    }
+}
+
+pub fn remove_verification_method(fragment: &str) -> Eip712Struct {
+    Eip712Struct {
+        name: "RemoveVerificationMethod",
+        members: "string fragment",
+        referenced_types: &[],
+        values: vec![encode_string(fragment)],
+    }
+}
+
+pub fn set_vm_flags(fragment: &str, flags: u16) -> Eip712Struct {
+    Eip712Struct {
+        name: "SetVmFlags",
+        members: "string fragment,uint16 flags",
+        referenced_types: &[],
+        values: vec![encode_string(fragment), encode_uint(flags.into())],
+    }
+}
+
+pub fn set_vm_type(fragment: &str, method_type: u8) -> Eip712Struct {
+    Eip712Struct {
+        name: "SetVmType",
+        members: "string fragment,uint8 methodType",
+        referenced_types: &[],
+        values: vec![encode_string(fragment), encode_uint(method_type.into())],
+    }
+}
+
+pub fn set_vm_key(fragment: &str, key_data: &[u8], method_type: Option<u8>) -> Eip712Struct {
+    Eip712Struct {
+        name: "SetVmKey",
+        members: "string fragment,bytes keyData,uint8[] methodType",
+        referenced_types: &[],
+        values: vec![
+            encode_string(fragment),
+            encode_bytes(key_data),
+            encode_array(method_type.map(|method_type| encode_uint(method_type.into()))),
+        ],
+    }
+}
+
+pub fn add_service(service: &Service, allow_overwrite: bool) -> Eip712Struct {
+    Eip712Struct {
+        name: "AddService",
+        members: "Service service,bool allowOverwrite",
+        referenced_types: &[SERVICE_TYPE, SERVICE_PROPERTY_TYPE],
+        values: vec![hash_service(service), encode_bool(allow_overwrite)],
+    }
+}
+
+pub fn update_service(
+    fragment: &str,
+    service_types: Option<&[String]>,
+    service_endpoint: Option<&ServiceEndpoint>,
+) -> Eip712Struct {
+    let (endpoint_kinds, endpoints): (Vec<_>, Vec<_>) = service_endpoint
+        .map(encode_service_endpoint)
+        .into_iter()
+        .map(|(kind, endpoint)| (encode_uint(kind), encode_string(endpoint)))
+        .unzip();
+    Eip712Struct {
+        name: "UpdateService",
+        members:
+            "string fragment,string[][] serviceTypes,uint8[] endpointKind,string[] serviceEndpoint",
+        referenced_types: &[],
+        values: vec![
+            encode_string(fragment),
+            encode_array(service_types.map(encode_strings)),
+            encode_array(endpoint_kinds),
+            encode_array(endpoints),
+        ],
+    }
+}
+
+pub fn remove_service(fragment: &str) -> Eip712Struct {
+    Eip712Struct {
+        name: "RemoveService",
+        members: "string fragment",
+        referenced_types: &[],
+        values: vec![encode_string(fragment)],
+    }
+}
+
+pub fn set_controllers(
+    native_controllers: &[Pubkey],
+    other_controllers: &[String],
+) -> Eip712Struct {
+    Eip712Struct {
+        name: "SetControllers",
+        members: "string[] nativeControllers,string[] otherControllers",
+        referenced_types: &[],
+        values: vec![
+            encode_controllers(native_controllers),
+            encode_strings(other_controllers),
+        ],
+    }
+}
+
+pub fn add_also_known_as(uri: &str) -> Eip712Struct {
+    Eip712Struct {
+        name: "AddAlsoKnownAs",
+        members: "string uri",
+        referenced_types: &[],
+        values: vec![encode_string(uri)],
+    }
+}
+
+pub fn remove_also_known_as(uri: &str) -> Eip712Struct {
+    Eip712Struct {
+        name: "RemoveAlsoKnownAs",
+        members: "string uri",
+        referenced_types: &[],
+        values: vec![encode_string(uri)],
+    }
+}
+
+pub fn add_context(uri: &str) -> Eip712Struct {
+    Eip712Struct {
+        name: "AddContext",
+        members: "string uri",
+        referenced_types: &[],
+        values: vec![encode_string(uri)],
+    }
+}
+
+pub fn remove_context(uri: &str) -> Eip712Struct {
+    Eip712Struct {
+        name: "RemoveContext",
+        members: "string uri",
+        referenced_types: &[],
+        values: vec![encode_string(uri)],
+    }
+}
+
+pub fn update(
+    verification_methods: &[VerificationMethod],
+    services: &[Service],
+    native_controllers: &[Pubkey],
+    other_controllers: &[String],
+) -> Eip712Struct {
+    Eip712Struct {
+        name: "Update",
+        members: "VerificationMethod[] verificationMethods,Service[] services,string[] nativeControllers,string[] otherControllers",
+        referenced_types: &[SERVICE_TYPE, SERVICE_PROPERTY_TYPE, VERIFICATION_METHOD_TYPE],
+        values: vec![
+            encode_array(verification_methods.iter().map(hash_verification_method)),
+            encode_array(services.iter().map(hash_service)),
+            encode_controllers(native_controllers),
+            encode_strings(other_controllers),
+        ],
+    }
+}
+
+pub fn resize(size: u32) -> Eip712Struct {
+    Eip712Struct {
+        name: "Resize",
+        members: "uint32 size",
+        referenced_types: &[],
+        values: vec![encode_uint(size.into())],
+    }
+}
+
+pub fn close(destination: &Pubkey) -> Eip712Struct {
+    Eip712Struct {
+        name: "Close",
+        members: "string destination",
+        referenced_types: &[],
+        values: vec![encode_string(&destination.to_string())],
+    }
+}
+
+pub fn compact(destination: &Pubkey) -> Eip712Struct {
+    Eip712Struct {
+        name: "Compact",
+        members: "string destination",
+        referenced_types: &[],
+        values: vec![encode_string(&destination.to_string())],
+    }
+}
+
+pub fn deactivate(destination: &Pubkey) -> Eip712Struct {
+    Eip712Struct {
+        name: "Deactivate",
+        members: "string destination",
+        referenced_types: &[],
+        values: vec![encode_string(&destination.to_string())],
+    }
+}
+
+pub fn rotate_initial_authority(new_authority: &Pubkey) -> Eip712Struct {
+    Eip712Struct {
+        name: "RotateInitialAuthority",
+        members: "string newAuthority",
+        referenced_types: &[],
+        values: vec![encode_string(&new_authority.to_string())],
+    }
+}
+
+pub fn set_authority_threshold(authority_threshold: u8) -> Eip712Struct {
+    Eip712Struct {
+        name: "SetAuthorityThreshold",
+        members: "uint8 authorityThreshold",
+        referenced_types: &[],
+        values: vec![encode_uint(authority_threshold.into())],
+    }
+}
+
+pub fn set_recovery_policy(recovery_policy: Option<&RecoveryPolicy>) -> Eip712Struct {
+    Eip712Struct {
+        name: "SetRecoveryPolicy",
+        members: "RecoveryPolicy[] recoveryPolicy",
+        referenced_types: &[RECOVERY_POLICY_TYPE],
+        values: vec![encode_array(recovery_policy.map(hash_recovery_policy))],
+    }
+}
+
+pub fn cancel_recovery(did_recovery: &Pubkey) -> Eip712Struct {
+    Eip712Struct {
+        name: "CancelRecovery",
+        members: "string didRecovery",
+        referenced_types: &[],
+        values: vec![encode_string(&did_recovery.to_string())],
+    }
+}
+
+pub fn batch(operations: &[BatchOperation]) -> Eip712Struct {
+    Eip712Struct {
+        name: "Batch",
+        members: "BatchOperation[] operations",
+        referenced_types: &[
+            BATCH_OPERATION_TYPE,
+            SERVICE_TYPE,
+            SERVICE_PROPERTY_TYPE,
+            VERIFICATION_METHOD_TYPE,
+        ],
+        values: vec![encode_array(operations.iter().map(hash_batch_operation))],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::eth_sign_hash;
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn encode_address(address: &str) -> [u8; 32] {
+        let mut encoded = [0u8; 32];
+        for (index, byte) in encoded[12..].iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&address[2 * index..2 * index + 2], 16).unwrap();
+        }
+        encoded
+    }
+
+    #[test]
+    fn test_eip712_reference_vector() {
+        // the "Mail" example of https://eips.ethereum.org/EIPS/eip-712, as produced by
+        // ethers `TypedDataEncoder.hash` and MetaMask `eth_signTypedData_v4`
+        let domain_separator = hash_struct(
+            "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+            &[
+                encode_string("Ether Mail"),
+                encode_string("1"),
+                encode_uint(1),
+                encode_address("CcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"),
+            ],
+        );
+        assert_eq!(
+            to_hex(&domain_separator),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+
+        let encoded_type =
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)";
+        let hash_person = |name: &str, wallet: &str| {
+            hash_struct(
+                "Person(string name,address wallet)",
+                &[encode_string(name), encode_address(wallet)],
+            )
+        };
+        let struct_hash = hash_struct(
+            encoded_type,
+            &[
+                hash_person("Cow", "CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+                hash_person("Bob", "bBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+                encode_string("Hello, Bob!"),
+            ],
+        );
+        assert_eq!(
+            to_hex(&struct_hash),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+
+        let signing_hash = encode_typed_data(&domain_separator, &struct_hash);
+        assert_eq!(
+            to_hex(&signing_hash),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+
+        // signed by keccak256("cow")
+        let secret_key = libsecp256k1::SecretKey::parse(&hash(b"cow").to_bytes()).unwrap();
+        let signature = eth_sign_hash(&secret_key, &signing_hash);
+        assert_eq!(
+            to_hex(&signature.signature),
+            "4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d\
+             07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562"
+        );
+        // v = 28
+        assert_eq!(signature.recovery_id, 1);
+    }
+
+    #[test]
+    fn test_batch_typed_data() {
+        let remove_service = |fragment: &str| BatchOperation::RemoveService {
+            fragment: fragment.to_string(),
+        };
+        let typed_data = batch(&[remove_service("service")]);
+        assert_eq!(
+            typed_data.encode_type(),
+            [
+                "Batch(string did,uint64 nonce,BatchOperation[] operations)",
+                BATCH_OPERATION_TYPE,
+                SERVICE_TYPE,
+                SERVICE_PROPERTY_TYPE,
+                VERIFICATION_METHOD_TYPE,
+            ]
+            .concat()
+        );
+
+        // bound to the operations, the DID and the nonce
+        let did = format!("did:sol:{}", Pubkey::new_unique());
+        let signing_hash = typed_data.signing_hash(&did, 0);
+        for other_signing_hash in [
+            batch(&[remove_service("other")]).signing_hash(&did, 0),
+            batch(&[BatchOperation::RemoveVerificationMethod {
+                fragment: "service".to_string(),
+            }])
+            .signing_hash(&did, 0),
+            batch(&[remove_service("service"), remove_service("service")]).signing_hash(&did, 0),
+            typed_data.signing_hash(&format!("did:sol:{}", Pubkey::new_unique()), 0),
+            typed_data.signing_hash(&did, 1),
+        ] {
+            assert_ne!(signing_hash, other_signing_hash);
+        }
+    }
+}
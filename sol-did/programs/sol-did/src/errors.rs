@@ -30,4 +30,56 @@ pub enum DidSolError {
     WrongAuthorityForDid,
     #[msg("Cannot remove a protected verification method. You need to first remove the Protected Verification Method Flag in order for this operation to succeed")]
     VmCannotRemoveProtected,
+    #[msg("Invalid validity window. valid_from must be before valid_until")]
+    VmInvalidValidityWindow,
+    #[msg("Not enough authorities signed to meet the authority threshold of the DID")]
+    AuthorityThresholdNotMet,
+    #[msg("Invalid authority threshold. The threshold must be at least 1")]
+    InvalidAuthorityThreshold,
+    #[msg("Invalid recovery policy. Guardians must be unique and must not include the DID itself. The threshold must be between 1 and the number of guardians")]
+    InvalidRecoveryPolicy,
+    #[msg("No recovery policy is set for this DID")]
+    RecoveryNotEnabled,
+    #[msg("The given key is not an authority of a guardian of this DID")]
+    InvalidGuardian,
+    #[msg("The guardian already approved this recovery")]
+    RecoveryAlreadyApproved,
+    #[msg("Not enough guardians approved this recovery")]
+    RecoveryThresholdNotMet,
+    #[msg("The challenge period of this recovery has not ended yet")]
+    RecoveryChallengePeriodActive,
+    #[msg("A recovery verification method must be a CAPABILITY_INVOCATION authority")]
+    InvalidRecoveryVerificationMethod,
+    #[msg("Unsupported DID account version")]
+    UnsupportedAccountVersion,
+    #[msg("Invalid key data for the verification method type")]
+    VmInvalidKeyData,
+    #[msg(
+        "The verification method type is not compatible with the key of the verification method"
+    )]
+    VmIncompatibleType,
+    #[msg("Cannot manage a verification method with permissions the authority does not have")]
+    VmPermissionsExceedAuthority,
+    #[msg("A batch must contain at least one operation")]
+    EmptyBatch,
+    #[msg("A batch can only change the guarded flags of a single verification method")]
+    BatchGuardedFlagsConflict,
+    #[msg(
+        "The DID account needs to grow. Pass a payer and the system program, or resize it first"
+    )]
+    MissingRentPayer,
+    #[msg("Invalid size. The DID account must be large enough for the serialized DID")]
+    InvalidAccountSize,
+    #[msg("Invalid service. A service needs a type, a URI or JSON map / set endpoint and unique properties")]
+    InvalidService,
+    #[msg("Invalid URI. URIs must be absolute, e.g. did:web:example.com or https://example.com")]
+    InvalidUri,
+    #[msg("The URI already exists")]
+    UriAlreadyInUse,
+    #[msg("The URI doesn't exist")]
+    UriNotFound,
+    #[msg("The recovery expired. Close it and start a new recovery")]
+    RecoveryExpired,
+    #[msg("The recovery can still be executed")]
+    RecoveryNotStale,
 }
@@ -0,0 +1,214 @@
+//! Events emitted by the did:sol program on every DID mutation.
+//!
+//! Every event carries the DID identifier (`did`, the initial authority of the DidAccount) and
+//! the DidAccount `nonce` after the instruction. Events of instructions that are authorized by a
+//! DID authority also carry the fragment of the authorizing verification method.
+use crate::state::VerificationMethod;
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct DidCreated {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct DidMigrated {
+    pub did: Pubkey,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct DidUpgraded {
+    pub did: Pubkey,
+    pub version: u8,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct DidUpdated {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct DidResized {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub size: u32,
+}
+
+#[event]
+pub struct DidClosed {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct DidDeactivated {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+}
+
+#[event]
+pub struct VerificationMethodAdded {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub fragment: String,
+}
+
+#[event]
+pub struct VerificationMethodRemoved {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub fragment: String,
+}
+
+#[event]
+pub struct VmFlagsChanged {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub fragment: String,
+    pub flags: u16,
+}
+
+#[event]
+pub struct VmKeyChanged {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub fragment: String,
+    pub method_type: u8,
+    /// The flags after the replacement, guarded flags are cleared unless the new key co-signed
+    pub flags: u16,
+}
+
+#[event]
+pub struct VmTypeChanged {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub fragment: String,
+    pub method_type: u8,
+}
+
+#[event]
+pub struct ServiceAdded {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub fragment: String,
+}
+
+#[event]
+pub struct ServiceUpdated {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub fragment: String,
+}
+
+#[event]
+pub struct ServiceRemoved {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub fragment: String,
+}
+
+#[event]
+pub struct ControllersChanged {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub native_controllers: Vec<Pubkey>,
+    pub other_controllers: Vec<String>,
+}
+
+#[event]
+pub struct AlsoKnownAsChanged {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub also_known_as: Vec<String>,
+}
+
+#[event]
+pub struct ContextsChanged {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub contexts: Vec<String>,
+}
+
+#[event]
+pub struct InitialAuthorityRotated {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityThresholdChanged {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub authority_threshold: u8,
+}
+
+#[event]
+pub struct RecoveryPolicyChanged {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+    pub enabled: bool,
+}
+
+/// Recovery events are authorized by guardians, which are identified by their DID instead of a fragment.
+#[event]
+pub struct RecoveryStarted {
+    pub did: Pubkey,
+    pub guardians: Vec<Pubkey>,
+    pub nonce: u64,
+    pub verification_method: VerificationMethod,
+}
+
+#[event]
+pub struct RecoveryApproved {
+    pub did: Pubkey,
+    pub guardian: Pubkey,
+    pub nonce: u64,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct RecoveryCancelled {
+    pub did: Pubkey,
+    pub authority_fragment: String,
+    pub nonce: u64,
+}
+
+/// A stale recovery is closed permissionlessly.
+#[event]
+pub struct RecoveryClosed {
+    pub did: Pubkey,
+    pub nonce: u64,
+}
+
+/// Recovery is executed permissionlessly, `fragment` is the recovered verification method.
+#[event]
+pub struct RecoveryExecuted {
+    pub did: Pubkey,
+    pub nonce: u64,
+    pub fragment: String,
+}
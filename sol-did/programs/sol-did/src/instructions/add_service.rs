@@ -1,20 +1,48 @@
-use crate::constants::DID_ACCOUNT_SEED;
-use crate::state::{DidAccount, Secp256k1RawSignature, Service};
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::ServiceAdded;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature, Service};
+use crate::utils::realloc_did_account;
 use anchor_lang::prelude::*;
 
-pub fn add_service(
-    ctx: Context<AddService>,
+pub fn add_service<'info>(
+    ctx: Context<'_, '_, '_, 'info, AddService<'info>>,
     service: Service,
     allow_overwrite: bool,
     eth_signature: Option<Secp256k1RawSignature>,
 ) -> Result<()> {
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "add_service",
+        &signed_message(&service, allow_overwrite),
+        Some(eip712::add_service(&service, allow_overwrite)),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::ADD_SERVICE,
+        ctx.remaining_accounts,
+    )?;
 
+    let fragment = service.fragment.clone();
     let joint_services = [&[service], data.services.as_slice()].concat();
-    data.set_services(joint_services, allow_overwrite)
+    data.set_services(joint_services, allow_overwrite)?;
+
+    realloc_did_account(
+        &data.to_account_info(),
+        8 + data.size(),
+        ctx.accounts.payer.as_ref(),
+        ctx.accounts.system_program.as_ref(),
+    )?;
+
+    emit!(ServiceAdded {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        fragment,
+    });
+
+    Ok(())
 }
 
 // TODO: In a way this is doing a "serialize again, after anchor deserialzes the transaction".
@@ -26,14 +54,17 @@ pub fn signed_message(service: &Service, allow_overwrite: bool) -> Vec<u8> {
 }
 
 #[derive(Accounts)]
-#[instruction(service: Service, allow_overwrite: bool, eth_signature: Option<Secp256k1RawSignature>)]
 pub struct AddService<'info> {
     #[account(
     mut,
-    seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_verification_method.key_data.as_ref()],
+    seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
     bump = did_data.bump,
-    constraint = did_data.find_authority_constraint(&authority.key(), &signed_message(&service, allow_overwrite), eth_signature.as_ref(), None).is_some(),
+    constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
     )]
     pub did_data: Account<'info, DidAccount>,
     pub authority: Signer<'info>,
+    /// Pays the rent if the DID account grows (optional)
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    pub system_program: Option<Program<'info, System>>,
 }
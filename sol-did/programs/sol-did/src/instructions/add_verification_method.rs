@@ -1,36 +1,64 @@
-use crate::constants::DID_ACCOUNT_SEED;
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
 
-use crate::state::{DidAccount, Secp256k1RawSignature, VerificationMethod};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::VerificationMethodAdded;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature, VerificationMethod};
+use crate::utils::realloc_did_account;
 use anchor_lang::prelude::*;
 
-pub fn add_verification_method(
-    ctx: Context<AddVerificationMethod>,
+pub fn add_verification_method<'info>(
+    ctx: Context<'_, '_, '_, 'info, AddVerificationMethod<'info>>,
     verification_method: VerificationMethod,
     eth_signature: Option<Secp256k1RawSignature>,
 ) -> Result<()> {
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "add_verification_method",
+        &verification_method.try_to_vec().unwrap(),
+        Some(eip712::add_verification_method(&verification_method)),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::ADD_VERIFICATION_METHOD,
+        ctx.remaining_accounts,
+    )?;
 
-    let existing = [
-        data.verification_methods.as_slice(),
-        &[data.initial_verification_method.clone()],
-    ]
-    .concat();
+    // the authority cannot grant permissions it does not have
+    authority.check_can_manage([&verification_method])?;
 
-    data.set_verification_methods(existing, vec![verification_method])
+    let fragment = verification_method.fragment.clone();
+    data.add_verification_method(verification_method)?;
+
+    realloc_did_account(
+        &data.to_account_info(),
+        8 + data.size(),
+        ctx.accounts.payer.as_ref(),
+        ctx.accounts.system_program.as_ref(),
+    )?;
+
+    emit!(VerificationMethodAdded {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        fragment,
+    });
+
+    Ok(())
 }
 
 #[derive(Accounts)]
-#[instruction(verification_method: VerificationMethod, eth_signature: Option<Secp256k1RawSignature>)]
 pub struct AddVerificationMethod<'info> {
     #[account(
         mut,
-        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_verification_method.key_data.as_ref()],
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
         bump = did_data.bump,
-        constraint = did_data.find_authority_constraint(&authority.key(), &verification_method.try_to_vec().unwrap(), eth_signature.as_ref(), None).is_some(),
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
     )]
     pub did_data: Account<'info, DidAccount>,
     pub authority: Signer<'info>,
+    /// Pays the rent if the DID account grows (optional)
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    pub system_program: Option<Program<'info, System>>,
 }
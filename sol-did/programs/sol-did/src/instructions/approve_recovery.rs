@@ -0,0 +1,55 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION, DID_RECOVERY_SEED};
+use crate::errors::DidSolError;
+use crate::events::RecoveryApproved;
+use crate::state::{DidAccount, DidRecovery};
+use anchor_lang::prelude::*;
+
+pub fn approve_recovery(ctx: Context<ApproveRecovery>, guardian: Pubkey) -> Result<()> {
+    let policy = ctx
+        .accounts
+        .did_data
+        .recovery_policy
+        .as_ref()
+        .ok_or_else(|| error!(DidSolError::RecoveryNotEnabled))?;
+    policy.check_guardian(
+        &guardian,
+        &ctx.accounts.guardian_did,
+        &ctx.accounts.guardian_authority.key(),
+    )?;
+
+    let recovery = &mut ctx.accounts.did_recovery;
+    require!(
+        !recovery.approvals.contains(&guardian),
+        DidSolError::RecoveryAlreadyApproved
+    );
+    recovery.approvals.push(guardian);
+
+    emit!(RecoveryApproved {
+        did: ctx.accounts.did_data.initial_authority,
+        guardian,
+        nonce: ctx.accounts.did_data.nonce,
+        approvals: policy.count_approvals(&recovery.approvals) as u8,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(guardian: Pubkey)]
+pub struct ApproveRecovery<'info> {
+    #[account(
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    #[account(
+        mut,
+        seeds = [DID_RECOVERY_SEED.as_bytes(), did_data.key().as_ref()],
+        bump = did_recovery.bump,
+    )]
+    pub did_recovery: Account<'info, DidRecovery>,
+    /// CHECK: The guardian DID account (generative or not) is checked against the recovery policy.
+    pub guardian_did: UncheckedAccount<'info>,
+    pub guardian_authority: Signer<'info>,
+}
@@ -0,0 +1,410 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::{
+    ControllersChanged, ServiceAdded, ServiceRemoved, ServiceUpdated, VerificationMethodAdded,
+    VerificationMethodRemoved, VmFlagsChanged,
+};
+use crate::instructions::{SetControllersArg, UpdateFlagsVerificationMethod, UpdateServiceArg};
+use crate::state::{
+    DidAccount, InstructionPermissions, Secp256k1RawSignature, Service, UpdateAuthority,
+    VerificationMethod,
+};
+use crate::utils::realloc_did_account;
+use anchor_lang::prelude::*;
+use itertools::Itertools;
+
+pub fn batch<'info>(
+    ctx: Context<'_, '_, '_, 'info, Batch<'info>>,
+    operations: Vec<BatchOperation>,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    require!(!operations.is_empty(), DidSolError::EmptyBatch);
+
+    // guarded flags can only be changed by the VM itself, so at most one VM per batch
+    let filter_fragments = operations
+        .iter()
+        .filter_map(|operation| operation.get_filter_fragment())
+        .unique()
+        .collect_vec();
+    require!(
+        filter_fragments.len() <= 1,
+        DidSolError::BatchGuardedFlagsConflict
+    );
+
+    // the authority needs the permissions of all operations
+    let permission = operations
+        .iter()
+        .fold(InstructionPermissions::empty(), |accum, operation| {
+            accum | operation.permission()
+        });
+
+    let data = &mut ctx.accounts.did_data;
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "batch",
+        &operations.try_to_vec().unwrap(),
+        Some(eip712::batch(&operations)),
+        eth_signature.as_ref(),
+        filter_fragments.first().copied(),
+        permission,
+        ctx.remaining_accounts,
+    )?;
+
+    apply_batch(data, operations, &authority)?;
+
+    realloc_did_account(
+        &data.to_account_info(),
+        8 + data.size(),
+        ctx.accounts.payer.as_ref(),
+        ctx.accounts.system_program.as_ref(),
+    )?;
+
+    Ok(())
+}
+
+/// Applies all operations in order, the DID must have an authority afterwards.
+pub fn apply_batch(
+    data: &mut DidAccount,
+    operations: Vec<BatchOperation>,
+    authority: &UpdateAuthority,
+) -> Result<()> {
+    for operation in operations {
+        operation.apply(data, authority)?;
+    }
+
+    // prevent lockout, intermediate states of the batch may have no authority
+    require!(
+        data.has_authority_verification_methods(),
+        DidSolError::VmCannotRemoveLastAuthority
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Batch<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+    /// Pays the rent if the DID account grows, receives the rent freed if it shrinks (optional)
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    pub system_program: Option<Program<'info, System>>,
+}
+
+/// Argument
+/// A single operation of a batch, with the same arguments as the corresponding instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum BatchOperation {
+    AddVerificationMethod {
+        verification_method: VerificationMethod,
+    },
+    RemoveVerificationMethod {
+        fragment: String,
+    },
+    SetVmFlags {
+        flags_vm: UpdateFlagsVerificationMethod,
+    },
+    AddService {
+        service: Service,
+        allow_overwrite: bool,
+    },
+    RemoveService {
+        fragment: String,
+    },
+    SetControllers {
+        set_controllers_arg: SetControllersArg,
+    },
+    UpdateService {
+        update_service_arg: UpdateServiceArg,
+    },
+}
+
+impl BatchOperation {
+    pub fn permission(&self) -> InstructionPermissions {
+        match self {
+            BatchOperation::AddVerificationMethod { .. } => {
+                InstructionPermissions::ADD_VERIFICATION_METHOD
+            }
+            BatchOperation::RemoveVerificationMethod { .. } => {
+                InstructionPermissions::REMOVE_VERIFICATION_METHOD
+            }
+            BatchOperation::SetVmFlags { .. } => InstructionPermissions::SET_VM_FLAGS,
+            BatchOperation::AddService { .. } => InstructionPermissions::ADD_SERVICE,
+            BatchOperation::RemoveService { .. } => InstructionPermissions::REMOVE_SERVICE,
+            BatchOperation::SetControllers { .. } => InstructionPermissions::SET_CONTROLLERS,
+            BatchOperation::UpdateService { .. } => InstructionPermissions::ADD_SERVICE,
+        }
+    }
+
+    pub fn get_filter_fragment(&self) -> Option<&String> {
+        match self {
+            BatchOperation::SetVmFlags { flags_vm } => flags_vm.get_filter_fragment(),
+            _ => None,
+        }
+    }
+
+    /// Applies the operation to the DID and emits the event of the corresponding instruction.
+    /// The lockout check is left to the caller.
+    fn apply(self, data: &mut DidAccount, authority: &UpdateAuthority) -> Result<()> {
+        let did = data.initial_authority;
+        match self {
+            BatchOperation::AddVerificationMethod {
+                verification_method,
+            } => {
+                authority.check_can_manage([&verification_method])?;
+
+                let fragment = verification_method.fragment.clone();
+                data.add_verification_method(verification_method)?;
+
+                emit!(VerificationMethodAdded {
+                    did,
+                    authority_fragment: authority.fragment.clone(),
+                    nonce: data.nonce,
+                    fragment,
+                });
+            }
+            BatchOperation::RemoveVerificationMethod { fragment } => {
+                // cannot remove protected verification methods
+                require!(
+                    !data.has_protected_verification_method(Some(&fragment)),
+                    DidSolError::VmCannotRemoveProtected
+                );
+                authority.check_can_manage(data.verification_methods(
+                    None,
+                    None,
+                    None,
+                    Some(&fragment),
+                ))?;
+
+                let _ = data.remove_verification_method(&fragment);
+
+                emit!(VerificationMethodRemoved {
+                    did,
+                    authority_fragment: authority.fragment.clone(),
+                    nonce: data.nonce,
+                    fragment,
+                });
+            }
+            BatchOperation::SetVmFlags { flags_vm } => {
+                authority.check_can_manage(data.verification_methods(
+                    None,
+                    None,
+                    None,
+                    Some(&flags_vm.fragment),
+                ))?;
+
+                let vm = data.find_verification_method(&flags_vm.fragment);
+                require!(vm.is_some(), DidSolError::VmFragmentNotFound);
+                vm.unwrap().flags = flags_vm.flags;
+
+                emit!(VmFlagsChanged {
+                    did,
+                    authority_fragment: authority.fragment.clone(),
+                    nonce: data.nonce,
+                    fragment: flags_vm.fragment,
+                    flags: flags_vm.flags,
+                });
+            }
+            BatchOperation::AddService {
+                service,
+                allow_overwrite,
+            } => {
+                let fragment = service.fragment.clone();
+                let joint_services = [&[service], data.services.as_slice()].concat();
+                data.set_services(joint_services, allow_overwrite)?;
+
+                emit!(ServiceAdded {
+                    did,
+                    authority_fragment: authority.fragment.clone(),
+                    nonce: data.nonce,
+                    fragment,
+                });
+            }
+            BatchOperation::RemoveService { fragment } => {
+                data.services
+                    .iter()
+                    .position(|service| service.fragment == *fragment)
+                    .map(|index| {
+                        data.services.remove(index);
+                    })
+                    .ok_or_else(|| error!(DidSolError::ServiceFragmentNotFound))?;
+
+                emit!(ServiceRemoved {
+                    did,
+                    authority_fragment: authority.fragment.clone(),
+                    nonce: data.nonce,
+                    fragment,
+                });
+            }
+            BatchOperation::SetControllers {
+                set_controllers_arg,
+            } => {
+                data.set_native_controllers(set_controllers_arg.native_controllers)?;
+                data.set_other_controllers(set_controllers_arg.other_controllers)?;
+
+                emit!(ControllersChanged {
+                    did,
+                    authority_fragment: authority.fragment.clone(),
+                    nonce: data.nonce,
+                    native_controllers: data.native_controllers.clone(),
+                    other_controllers: data.other_controllers.clone(),
+                });
+            }
+            BatchOperation::UpdateService { update_service_arg } => {
+                data.update_service(
+                    &update_service_arg.fragment,
+                    update_service_arg.service_types,
+                    update_service_arg.service_endpoint,
+                )?;
+
+                emit!(ServiceUpdated {
+                    did,
+                    authority_fragment: authority.fragment.clone(),
+                    nonce: data.nonce,
+                    fragment: update_service_arg.fragment,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::VM_DEFAULT_FRAGMENT_NAME;
+    use crate::state::VerificationMethodFlags;
+
+    fn create_test_vm(fragment: &str, key: &Pubkey) -> VerificationMethod {
+        VerificationMethod {
+            fragment: fragment.to_string(),
+            flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+            method_type: 0,
+            key_data: key.to_bytes().to_vec(),
+            valid_from: None,
+            valid_until: None,
+            permissions: None,
+        }
+    }
+
+    /// A DID whose default VM is not protected
+    fn create_test_did(authority: &Pubkey) -> DidAccount {
+        let mut did = DidAccount::new(255, authority);
+        did.initial_verification_method.flags =
+            VerificationMethodFlags::CAPABILITY_INVOCATION.bits();
+        did
+    }
+
+    fn remove_default_vm() -> BatchOperation {
+        BatchOperation::RemoveVerificationMethod {
+            fragment: VM_DEFAULT_FRAGMENT_NAME.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_batch_rejects_lockout() {
+        let authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        let update_authority = UpdateAuthority::from(&did.initial_verification_method);
+
+        assert_eq!(
+            apply_batch(&mut did, vec![remove_default_vm()], &update_authority).unwrap_err(),
+            error!(DidSolError::VmCannotRemoveLastAuthority)
+        );
+
+        // a VM without capability invocation is no authority
+        let mut did = create_test_did(&authority);
+        let mut key_agreement = create_test_vm("key-agreement", &Pubkey::new_unique());
+        key_agreement.flags = VerificationMethodFlags::KEY_AGREEMENT.bits();
+        assert_eq!(
+            apply_batch(
+                &mut did,
+                vec![
+                    BatchOperation::AddVerificationMethod {
+                        verification_method: key_agreement,
+                    },
+                    remove_default_vm(),
+                ],
+                &update_authority,
+            )
+            .unwrap_err(),
+            error!(DidSolError::VmCannotRemoveLastAuthority)
+        );
+    }
+
+    #[test]
+    fn test_batch_allows_intermediate_states_without_authority() {
+        let authority = Pubkey::new_unique();
+        let other_authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        let update_authority = UpdateAuthority::from(&did.initial_verification_method);
+
+        // the DID has no authority after the first operation
+        apply_batch(
+            &mut did,
+            vec![
+                remove_default_vm(),
+                BatchOperation::AddVerificationMethod {
+                    verification_method: create_test_vm("other", &other_authority),
+                },
+            ],
+            &update_authority,
+        )
+        .unwrap();
+        assert!(did
+            .find_authority(&authority.to_bytes(), None, None)
+            .is_none());
+        assert!(did
+            .find_authority(&other_authority.to_bytes(), None, None)
+            .is_some());
+    }
+
+    #[test]
+    fn test_batch_operation_permissions() {
+        let operations = [
+            remove_default_vm(),
+            BatchOperation::RemoveService {
+                fragment: "service".to_string(),
+            },
+        ];
+        let permission = operations
+            .iter()
+            .fold(InstructionPermissions::empty(), |accum, operation| {
+                accum | operation.permission()
+            });
+        assert_eq!(
+            permission,
+            InstructionPermissions::REMOVE_VERIFICATION_METHOD
+                | InstructionPermissions::REMOVE_SERVICE
+        );
+
+        // a restricted authority can not apply operations of other VMs
+        let authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        did.add_verification_method(create_test_vm("other", &Pubkey::new_unique()))
+            .unwrap();
+        let update_authority = UpdateAuthority {
+            fragment: "restricted".to_string(),
+            permissions: InstructionPermissions::REMOVE_VERIFICATION_METHOD,
+        };
+        assert_eq!(
+            apply_batch(
+                &mut did,
+                vec![BatchOperation::RemoveVerificationMethod {
+                    fragment: "other".to_string(),
+                }],
+                &update_authority,
+            )
+            .unwrap_err(),
+            error!(DidSolError::VmPermissionsExceedAuthority)
+        );
+    }
+}
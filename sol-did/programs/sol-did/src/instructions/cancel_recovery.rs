@@ -0,0 +1,53 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION, DID_RECOVERY_SEED};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::RecoveryCancelled;
+use crate::state::{DidAccount, DidRecovery, InstructionPermissions, Secp256k1RawSignature};
+use anchor_lang::prelude::*;
+
+pub fn cancel_recovery(
+    ctx: Context<CancelRecovery>,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "cancel_recovery",
+        &ctx.accounts.did_recovery.key().to_bytes(),
+        Some(eip712::cancel_recovery(&ctx.accounts.did_recovery.key())),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::CANCEL_RECOVERY,
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(RecoveryCancelled {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        close = destination,
+        seeds = [DID_RECOVERY_SEED.as_bytes(), did_data.key().as_ref()],
+        bump = did_recovery.bump,
+    )]
+    pub did_recovery: Account<'info, DidRecovery>,
+    /// CHECK: The rent is returned to the payer of the recovery.
+    #[account(mut, address = did_recovery.payer)]
+    pub destination: UncheckedAccount<'info>,
+}
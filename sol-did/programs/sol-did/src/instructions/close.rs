@@ -1,25 +1,41 @@
-use crate::constants::DID_ACCOUNT_SEED;
-use crate::state::{DidAccount, Secp256k1RawSignature};
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::DidClosed;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
 use anchor_lang::prelude::*;
 
 pub fn close(ctx: Context<Close>, eth_signature: Option<Secp256k1RawSignature>) -> Result<()> {
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "close",
+        &ctx.accounts.destination.key().to_bytes(),
+        Some(eip712::close(&ctx.accounts.destination.key())),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::CLOSE,
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(DidClosed {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        destination: ctx.accounts.destination.key(),
+    });
 
     Ok(())
 }
 
 #[derive(Accounts)]
-#[instruction(eth_signature: Option<Secp256k1RawSignature>)]
 pub struct Close<'info> {
     #[account(
         mut,
         close = destination,
-        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_verification_method.key_data.as_ref()],
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
         bump = did_data.bump,
-        constraint = did_data.find_authority_constraint(&authority.key(), &[], eth_signature.as_ref(), None).is_some(),
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
     )]
     pub did_data: Account<'info, DidAccount>,
     pub authority: Signer<'info>,
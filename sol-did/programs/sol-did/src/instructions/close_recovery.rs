@@ -0,0 +1,42 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION, DID_RECOVERY_SEED};
+use crate::errors::DidSolError;
+use crate::events::RecoveryClosed;
+use crate::state::{DidAccount, DidRecovery};
+use anchor_lang::prelude::*;
+
+pub fn close_recovery(ctx: Context<CloseRecovery>) -> Result<()> {
+    let data = &ctx.accounts.did_data;
+    require!(
+        ctx.accounts
+            .did_recovery
+            .is_stale(data, Clock::get()?.unix_timestamp),
+        DidSolError::RecoveryNotStale
+    );
+
+    emit!(RecoveryClosed {
+        did: data.initial_authority,
+        nonce: data.nonce,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseRecovery<'info> {
+    #[account(
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    #[account(
+        mut,
+        close = destination,
+        seeds = [DID_RECOVERY_SEED.as_bytes(), did_data.key().as_ref()],
+        bump = did_recovery.bump,
+    )]
+    pub did_recovery: Account<'info, DidRecovery>,
+    /// CHECK: The rent is returned to the payer of the recovery.
+    #[account(mut, address = did_recovery.payer)]
+    pub destination: UncheckedAccount<'info>,
+}
@@ -0,0 +1,50 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::DidResized;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use anchor_lang::prelude::*;
+
+/// Shrinks the DID account to the minimal size of the serialized DID.
+/// The excess rent is returned to `destination`.
+pub fn compact(ctx: Context<Compact>, eth_signature: Option<Secp256k1RawSignature>) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "compact",
+        &ctx.accounts.destination.key().to_bytes(),
+        Some(eip712::compact(&ctx.accounts.destination.key())),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::RESIZE,
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(DidResized {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        size: data.to_account_info().data_len() as u32,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Compact<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+        realloc = 8 + did_data.size(),
+        realloc::payer = destination,
+        realloc::zero = false,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+    /// CHECK: Rent destination account does not need to satisfy the any constraints.
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,54 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::DidDeactivated;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use anchor_lang::prelude::*;
+
+pub fn deactivate(
+    ctx: Context<Deactivate>,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "deactivate",
+        &ctx.accounts.destination.key().to_bytes(),
+        Some(eip712::deactivate(&ctx.accounts.destination.key())),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::DEACTIVATE,
+        ctx.remaining_accounts,
+    )?;
+
+    // The account is kept (as opposed to close), so the DID cannot be initialized
+    // again or fall back to a generative DID.
+    data.deactivate();
+
+    emit!(DidDeactivated {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Deactivate<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+        realloc = 8 + did_data.tombstone_size(),
+        realloc::payer = destination,
+        realloc::zero = false,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+    /// CHECK: Rent destination account does not need to satisfy the any constraints.
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,55 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION, DID_RECOVERY_SEED};
+use crate::errors::DidSolError;
+use crate::events::RecoveryExecuted;
+use crate::state::{DidAccount, DidRecovery};
+use anchor_lang::prelude::*;
+
+pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    let recovery = &ctx.accounts.did_recovery;
+    data.record_update()?;
+    let policy = data
+        .recovery_policy
+        .as_ref()
+        .ok_or_else(|| error!(DidSolError::RecoveryNotEnabled))?;
+
+    recovery.check_executable(policy, Clock::get()?.unix_timestamp)?;
+
+    // the authority threshold is kept, a threshold of N needs N recovered verification methods
+    data.add_verification_method(recovery.verification_method.clone())?;
+
+    emit!(RecoveryExecuted {
+        did: data.initial_authority,
+        nonce: data.nonce,
+        fragment: recovery.verification_method.fragment.clone(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+        realloc = std::cmp::max(did_data.to_account_info().data_len(), 8 + did_data.size() + did_recovery.verification_method.size()),
+        realloc::payer = payer,
+        realloc::zero = false,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    #[account(
+        mut,
+        close = destination,
+        seeds = [DID_RECOVERY_SEED.as_bytes(), did_data.key().as_ref()],
+        bump = did_recovery.bump,
+    )]
+    pub did_recovery: Account<'info, DidRecovery>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: The rent is returned to the payer of the recovery.
+    #[account(mut, address = did_recovery.payer)]
+    pub destination: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
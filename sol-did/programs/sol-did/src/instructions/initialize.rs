@@ -1,5 +1,6 @@
 use crate::constants::DID_ACCOUNT_SEED;
 use crate::errors::DidSolError;
+use crate::events::DidCreated;
 use crate::state::{DidAccount, VerificationMethodFlags};
 use anchor_lang::prelude::*;
 
@@ -18,6 +19,13 @@ pub fn initialize(ctx: Context<Initialize>, size: u32) -> Result<()> {
             | VerificationMethodFlags::OWNERSHIP_PROOF
             | VerificationMethodFlags::PROTECTED,
     );
+    data.record_creation()?;
+
+    emit!(DidCreated {
+        did: data.initial_authority,
+        authority_fragment: data.initial_verification_method.fragment.clone(),
+        nonce: data.nonce,
+    });
 
     Ok(())
 }
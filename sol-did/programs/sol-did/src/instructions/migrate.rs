@@ -1,4 +1,5 @@
 use crate::constants::DID_ACCOUNT_SEED;
+use crate::events::DidMigrated;
 use crate::legacy::LegacyDidAccount;
 use crate::state::DidAccount;
 use anchor_lang::prelude::*;
@@ -9,6 +10,12 @@ pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
 
     let bump = *ctx.bumps.get("did_data").unwrap();
     legacy_data.migrate(data, bump)?;
+    data.record_creation()?;
+
+    emit!(DidMigrated {
+        did: data.initial_authority,
+        nonce: data.nonce,
+    });
 
     Ok(())
 }
@@ -1,23 +1,61 @@
+mod add_also_known_as;
+mod add_context;
 mod add_service;
 mod add_verification_method;
+mod approve_recovery;
+mod batch;
+mod cancel_recovery;
 mod close;
+mod close_recovery;
+mod compact;
+mod deactivate;
+mod execute_recovery;
 mod initialize;
 mod migrate;
+mod remove_also_known_as;
+mod remove_context;
 mod remove_service;
 mod remove_verification_method;
 mod resize;
+mod rotate_initial_authority;
+mod set_authority_threshold;
 mod set_controllers;
+mod set_recovery_policy;
 mod set_vm_flags;
+mod set_vm_key;
+mod set_vm_type;
+mod start_recovery;
 mod update;
+mod update_service;
+mod upgrade_account;
 
+pub use add_also_known_as::*;
+pub use add_context::*;
 pub use add_service::*;
 pub use add_verification_method::*;
+pub use approve_recovery::*;
+pub use batch::*;
+pub use cancel_recovery::*;
 pub use close::*;
+pub use close_recovery::*;
+pub use compact::*;
+pub use deactivate::*;
+pub use execute_recovery::*;
 pub use initialize::*;
 pub use migrate::*;
+pub use remove_also_known_as::*;
+pub use remove_context::*;
 pub use remove_service::*;
 pub use remove_verification_method::*;
 pub use resize::*;
+pub use rotate_initial_authority::*;
+pub use set_authority_threshold::*;
 pub use set_controllers::*;
+pub use set_recovery_policy::*;
 pub use set_vm_flags::*;
+pub use set_vm_key::*;
+pub use set_vm_type::*;
+pub use start_recovery::*;
 pub use update::*;
+pub use update_service::*;
+pub use upgrade_account::*;
@@ -0,0 +1,58 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::AlsoKnownAsChanged;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use crate::utils::realloc_did_account;
+use anchor_lang::prelude::*;
+
+pub fn remove_also_known_as<'info>(
+    ctx: Context<'_, '_, '_, 'info, RemoveAlsoKnownAs<'info>>,
+    uri: String,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "remove_also_known_as",
+        &uri.try_to_vec().unwrap(),
+        Some(eip712::remove_also_known_as(&uri)),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::SET_ALSO_KNOWN_AS,
+        ctx.remaining_accounts,
+    )?;
+
+    data.remove_also_known_as(&uri)?;
+
+    realloc_did_account(
+        &data.to_account_info(),
+        8 + data.size(),
+        ctx.accounts.destination.as_ref(),
+        None,
+    )?;
+
+    emit!(AlsoKnownAsChanged {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        also_known_as: data.also_known_as.clone(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RemoveAlsoKnownAs<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+    /// Receives the rent freed by shrinking the DID account (optional)
+    #[account(mut)]
+    pub destination: Option<Signer<'info>>,
+}
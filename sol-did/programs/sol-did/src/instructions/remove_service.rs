@@ -1,17 +1,27 @@
-use crate::constants::DID_ACCOUNT_SEED;
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
 use crate::errors::DidSolError;
-use crate::state::{DidAccount, Secp256k1RawSignature};
+use crate::events::ServiceRemoved;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use crate::utils::realloc_did_account;
 use anchor_lang::prelude::*;
 
-pub fn remove_service(
-    ctx: Context<RemoveService>,
+pub fn remove_service<'info>(
+    ctx: Context<'_, '_, '_, 'info, RemoveService<'info>>,
     fragment: String,
     eth_signature: Option<Secp256k1RawSignature>,
 ) -> Result<()> {
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "remove_service",
+        &fragment.try_to_vec().unwrap(),
+        Some(eip712::remove_service(&fragment)),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::REMOVE_SERVICE,
+        ctx.remaining_accounts,
+    )?;
 
     data.services
         .iter()
@@ -19,18 +29,36 @@ pub fn remove_service(
         .map(|index| {
             data.services.remove(index);
         })
-        .ok_or_else(|| error!(DidSolError::ServiceFragmentNotFound))
+        .ok_or_else(|| error!(DidSolError::ServiceFragmentNotFound))?;
+
+    realloc_did_account(
+        &data.to_account_info(),
+        8 + data.size(),
+        ctx.accounts.destination.as_ref(),
+        None,
+    )?;
+
+    emit!(ServiceRemoved {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        fragment,
+    });
+
+    Ok(())
 }
 
 #[derive(Accounts)]
-#[instruction(service_id: String, eth_signature: Option<Secp256k1RawSignature>)]
 pub struct RemoveService<'info> {
     #[account(
         mut,
-        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_verification_method.key_data.as_ref()],
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
         bump = did_data.bump,
-        constraint = did_data.find_authority_constraint(&authority.key(), &service_id.try_to_vec().unwrap(), eth_signature.as_ref(), None).is_some(),
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
     )]
     pub did_data: Account<'info, DidAccount>,
     pub authority: Signer<'info>,
+    /// Receives the rent freed by shrinking the DID account (optional)
+    #[account(mut)]
+    pub destination: Option<Signer<'info>>,
 }
@@ -1,17 +1,27 @@
-use crate::constants::DID_ACCOUNT_SEED;
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
 use crate::errors::DidSolError;
-use crate::state::{DidAccount, Secp256k1RawSignature};
+use crate::events::VerificationMethodRemoved;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use crate::utils::realloc_did_account;
 use anchor_lang::prelude::*;
 
-pub fn remove_verification_method(
-    ctx: Context<RemoveVerificationMethod>,
+pub fn remove_verification_method<'info>(
+    ctx: Context<'_, '_, '_, 'info, RemoveVerificationMethod<'info>>,
     fragment: String,
     eth_signature: Option<Secp256k1RawSignature>,
 ) -> Result<()> {
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "remove_verification_method",
+        &fragment.try_to_vec().unwrap(),
+        Some(eip712::remove_verification_method(&fragment)),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::REMOVE_VERIFICATION_METHOD,
+        ctx.remaining_accounts,
+    )?;
 
     // cannot remove protected verification methods
     require!(
@@ -19,6 +29,9 @@ pub fn remove_verification_method(
         DidSolError::VmCannotRemoveProtected
     );
 
+    // cannot remove verification methods with more permissions than the authority
+    authority.check_can_manage(data.verification_methods(None, None, None, Some(&fragment)))?;
+
     let _ = data.remove_verification_method(&fragment);
 
     // prevent lockout
@@ -27,18 +40,34 @@ pub fn remove_verification_method(
         DidSolError::VmCannotRemoveLastAuthority
     );
 
+    realloc_did_account(
+        &data.to_account_info(),
+        8 + data.size(),
+        ctx.accounts.destination.as_ref(),
+        None,
+    )?;
+
+    emit!(VerificationMethodRemoved {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        fragment,
+    });
+
     Ok(())
 }
 
 #[derive(Accounts)]
-#[instruction(fragment: String, eth_signature: Option<Secp256k1RawSignature>)]
 pub struct RemoveVerificationMethod<'info> {
     #[account(
         mut,
-        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_verification_method.key_data.as_ref()],
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
         bump = did_data.bump,
-        constraint = did_data.find_authority_constraint(&authority.key(), &fragment.try_to_vec().unwrap(), eth_signature.as_ref(), None).is_some(),
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
     )]
     pub did_data: Account<'info, DidAccount>,
     pub authority: Signer<'info>,
+    /// Receives the rent freed by shrinking the DID account (optional)
+    #[account(mut)]
+    pub destination: Option<Signer<'info>>,
 }
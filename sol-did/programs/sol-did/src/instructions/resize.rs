@@ -1,31 +1,51 @@
-use crate::constants::DID_ACCOUNT_SEED;
-use crate::state::{DidAccount, Secp256k1RawSignature};
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::DidResized;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use crate::utils::check_did_account_size;
 use anchor_lang::prelude::*;
-use std::convert::TryInto;
 
 pub fn resize(
     ctx: Context<Resize>,
-    _size: u32,
+    size: u32,
     eth_signature: Option<Secp256k1RawSignature>,
 ) -> Result<()> {
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "resize",
+        &size.to_le_bytes(),
+        Some(eip712::resize(size)),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::RESIZE,
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(DidResized {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        size: data.to_account_info().data_len() as u32,
+    });
+
     Ok(())
 }
 
 #[derive(Accounts)]
-#[instruction(size: u32, eth_signature: Option<Secp256k1RawSignature>)]
+#[instruction(size: u32)]
 pub struct Resize<'info> {
     #[account(
         mut,
-        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_verification_method.key_data.as_ref()],
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
         bump = did_data.bump,
-        realloc = TryInto::<usize>::try_into(size).unwrap(),
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+        // the serialized DID must still fit after shrinking. Growth beyond
+        // MAX_PERMITTED_DATA_INCREASE fails, it needs several resize instructions
+        realloc = check_did_account_size(size, did_data.size())?,
         realloc::payer = payer,
         realloc::zero = false,
-        constraint = did_data.find_authority_constraint(&authority.key(), &size.to_le_bytes(), eth_signature.as_ref(), None).is_some(),
     )]
     pub did_data: Account<'info, DidAccount>,
     #[account(mut)]
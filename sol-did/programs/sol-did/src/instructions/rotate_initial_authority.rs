@@ -0,0 +1,56 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::InitialAuthorityRotated;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use anchor_lang::prelude::*;
+
+pub fn rotate_initial_authority(
+    ctx: Context<RotateInitialAuthority>,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let new_authority = ctx.accounts.new_authority.key();
+    let data = &mut ctx.accounts.did_data;
+    // a default VM with guarded flags can only be rotated by itself
+    let default_fragment = data.initial_verification_method.fragment.clone();
+    let guarded = data.has_guarded_verification_method(&default_fragment);
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "rotate_initial_authority",
+        &new_authority.to_bytes(),
+        Some(eip712::rotate_initial_authority(&new_authority)),
+        eth_signature.as_ref(),
+        if guarded {
+            Some(&default_fragment)
+        } else {
+            None
+        },
+        InstructionPermissions::ROTATE_INITIAL_AUTHORITY,
+        ctx.remaining_accounts,
+    )?;
+
+    // The new authority co-signs, so the default VM keeps its OWNERSHIP_PROOF and PROTECTED flags.
+    data.rotate_initial_authority(&new_authority);
+
+    emit!(InitialAuthorityRotated {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        new_authority,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RotateInitialAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+    pub new_authority: Signer<'info>,
+}
@@ -0,0 +1,53 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::AuthorityThresholdChanged;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use anchor_lang::prelude::*;
+
+pub fn set_authority_threshold(
+    ctx: Context<SetAuthorityThreshold>,
+    authority_threshold: u8,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "set_authority_threshold",
+        &[authority_threshold],
+        Some(eip712::set_authority_threshold(authority_threshold)),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::SET_AUTHORITY_THRESHOLD,
+        ctx.remaining_accounts,
+    )?;
+
+    data.set_authority_threshold(authority_threshold)?;
+
+    // prevent lockout
+    require!(
+        data.has_authority_verification_methods(),
+        DidSolError::VmCannotRemoveLastAuthority
+    );
+
+    emit!(AuthorityThresholdChanged {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        authority_threshold,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetAuthorityThreshold<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+}
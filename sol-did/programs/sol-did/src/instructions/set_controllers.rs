@@ -1,5 +1,8 @@
-use crate::constants::DID_ACCOUNT_SEED;
-use crate::state::{DidAccount, Secp256k1RawSignature};
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::ControllersChanged;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
 use anchor_lang::prelude::*;
 
 pub fn set_controllers(
@@ -8,24 +11,41 @@ pub fn set_controllers(
     eth_signature: Option<Secp256k1RawSignature>,
 ) -> Result<()> {
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "set_controllers",
+        &set_controllers_arg.try_to_vec().unwrap(),
+        Some(eip712::set_controllers(
+            &set_controllers_arg.native_controllers,
+            &set_controllers_arg.other_controllers,
+        )),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::SET_CONTROLLERS,
+        ctx.remaining_accounts,
+    )?;
 
     data.set_native_controllers(set_controllers_arg.native_controllers)?;
     data.set_other_controllers(set_controllers_arg.other_controllers)?;
 
+    emit!(ControllersChanged {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        native_controllers: data.native_controllers.clone(),
+        other_controllers: data.other_controllers.clone(),
+    });
+
     Ok(())
 }
 
 #[derive(Accounts)]
-#[instruction(set_controllers_arg: SetControllersArg, eth_signature: Option<Secp256k1RawSignature>)]
 pub struct SetControllers<'info> {
     #[account(
         mut,
-        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_verification_method.key_data.as_ref()],
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
         bump = did_data.bump,
-        constraint = did_data.find_authority_constraint(&authority.key(), &set_controllers_arg.try_to_vec().unwrap(), eth_signature.as_ref(), None).is_some(),
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
     )]
     pub did_data: Account<'info, DidAccount>,
     pub authority: Signer<'info>,
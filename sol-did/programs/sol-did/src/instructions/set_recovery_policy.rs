@@ -0,0 +1,47 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::RecoveryPolicyChanged;
+use crate::state::{DidAccount, InstructionPermissions, RecoveryPolicy, Secp256k1RawSignature};
+use anchor_lang::prelude::*;
+
+pub fn set_recovery_policy(
+    ctx: Context<SetRecoveryPolicy>,
+    recovery_policy: Option<RecoveryPolicy>,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "set_recovery_policy",
+        &recovery_policy.try_to_vec().unwrap(),
+        Some(eip712::set_recovery_policy(recovery_policy.as_ref())),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::SET_RECOVERY_POLICY,
+        ctx.remaining_accounts,
+    )?;
+
+    data.set_recovery_policy(recovery_policy)?;
+
+    emit!(RecoveryPolicyChanged {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        enabled: data.recovery_policy.is_some(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetRecoveryPolicy<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+}
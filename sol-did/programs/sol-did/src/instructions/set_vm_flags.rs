@@ -1,6 +1,10 @@
-use crate::constants::DID_ACCOUNT_SEED;
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
 use crate::errors::DidSolError;
-use crate::state::{DidAccount, Secp256k1RawSignature, VerificationMethodFlags};
+use crate::events::VmFlagsChanged;
+use crate::state::{
+    DidAccount, InstructionPermissions, Secp256k1RawSignature, VerificationMethodFlags,
+};
 use anchor_lang::prelude::*;
 
 pub fn set_vm_flags(
@@ -9,9 +13,23 @@ pub fn set_vm_flags(
     eth_signature: Option<Secp256k1RawSignature>,
 ) -> Result<()> {
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "set_vm_flags",
+        &flags_vm.try_to_vec().unwrap(),
+        Some(eip712::set_vm_flags(&flags_vm.fragment, flags_vm.flags)),
+        eth_signature.as_ref(),
+        flags_vm.get_filter_fragment(),
+        InstructionPermissions::SET_VM_FLAGS,
+        ctx.remaining_accounts,
+    )?;
+
+    authority.check_can_manage(data.verification_methods(
+        None,
+        None,
+        None,
+        Some(&flags_vm.fragment),
+    ))?;
 
     let vm = data.find_verification_method(&flags_vm.fragment);
     require!(vm.is_some(), DidSolError::VmFragmentNotFound);
@@ -24,16 +42,23 @@ pub fn set_vm_flags(
         DidSolError::VmCannotRemoveLastAuthority
     );
 
+    emit!(VmFlagsChanged {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        fragment: flags_vm.fragment,
+        flags: flags_vm.flags,
+    });
+
     Ok(())
 }
 #[derive(Accounts)]
-#[instruction(flags_vm: UpdateFlagsVerificationMethod, eth_signature: Option<Secp256k1RawSignature>)]
 pub struct SetVmFlagsMethod<'info> {
     #[account(
         mut,
-        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_verification_method.key_data.as_ref()],
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
         bump = did_data.bump,
-        constraint = did_data.find_authority_constraint(&authority.key(), &flags_vm.try_to_vec().unwrap(), eth_signature.as_ref(), flags_vm.get_filter_fragment()).is_some(),
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
     )]
     pub did_data: Account<'info, DidAccount>,
     pub authority: Signer<'info>,
@@ -0,0 +1,111 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::VmKeyChanged;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
+use crate::utils::realloc_did_account;
+use anchor_lang::prelude::*;
+
+pub fn set_vm_key<'info>(
+    ctx: Context<'_, '_, '_, 'info, SetVmKeyMethod<'info>>,
+    key_vm: UpdateKeyVerificationMethod,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    // VMs with guarded flags (e.g. the default VM) can only be replaced by themselves
+    let guarded = data.has_guarded_verification_method(&key_vm.fragment);
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "set_vm_key",
+        &key_vm.try_to_vec().unwrap(),
+        Some(eip712::set_vm_key(
+            &key_vm.fragment,
+            &key_vm.key_data,
+            key_vm.method_type,
+        )),
+        eth_signature.as_ref(),
+        if guarded {
+            Some(&key_vm.fragment)
+        } else {
+            None
+        },
+        InstructionPermissions::SET_VM_KEY,
+        ctx.remaining_accounts,
+    )?;
+
+    authority.check_can_manage(data.verification_methods(
+        None,
+        None,
+        None,
+        Some(&key_vm.fragment),
+    ))?;
+
+    // the new key co-signs as the authority or as an additional signer
+    let signers: Vec<Pubkey> = std::iter::once(ctx.accounts.authority.key())
+        .chain(
+            ctx.remaining_accounts
+                .iter()
+                .filter(|account| account.is_signer)
+                .map(|account| account.key()),
+        )
+        .collect();
+    data.set_verification_method_key(
+        &key_vm.fragment,
+        key_vm.key_data,
+        key_vm.method_type,
+        &signers,
+    )?;
+
+    // prevent lockout
+    require!(
+        data.has_authority_verification_methods(),
+        DidSolError::VmCannotRemoveLastAuthority
+    );
+
+    realloc_did_account(
+        &data.to_account_info(),
+        8 + data.size(),
+        ctx.accounts.payer.as_ref(),
+        ctx.accounts.system_program.as_ref(),
+    )?;
+
+    let vm = data
+        .find_verification_method(&key_vm.fragment)
+        .ok_or_else(|| error!(DidSolError::VmFragmentNotFound))?;
+    let (method_type, flags) = (vm.method_type, vm.flags);
+    emit!(VmKeyChanged {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        fragment: key_vm.fragment,
+        method_type,
+        flags,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetVmKeyMethod<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+    /// Pays the rent if the DID account grows, receives the rent freed if it shrinks (optional)
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    pub system_program: Option<Program<'info, System>>,
+}
+
+/// Argument
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
+pub struct UpdateKeyVerificationMethod {
+    pub fragment: String,
+    pub key_data: Vec<u8>,
+    /// The new type of the verification method, the type is kept if `None`
+    pub method_type: Option<u8>,
+}
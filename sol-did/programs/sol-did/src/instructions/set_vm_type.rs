@@ -0,0 +1,82 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::VmTypeChanged;
+use crate::state::{
+    DidAccount, InstructionPermissions, Secp256k1RawSignature, VerificationMethodType,
+};
+use anchor_lang::prelude::*;
+use num_traits::FromPrimitive;
+
+pub fn set_vm_type(
+    ctx: Context<SetVmTypeMethod>,
+    type_vm: UpdateTypeVerificationMethod,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    // VMs with guarded flags (e.g. the default VM) can only be re-typed by themselves
+    let guarded = data.has_guarded_verification_method(&type_vm.fragment);
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "set_vm_type",
+        &type_vm.try_to_vec().unwrap(),
+        Some(eip712::set_vm_type(&type_vm.fragment, type_vm.method_type)),
+        eth_signature.as_ref(),
+        if guarded {
+            Some(&type_vm.fragment)
+        } else {
+            None
+        },
+        InstructionPermissions::SET_VM_TYPE,
+        ctx.remaining_accounts,
+    )?;
+
+    let new_type =
+        VerificationMethodType::from_u8(type_vm.method_type).ok_or(DidSolError::ConversionError)?;
+    authority.check_can_manage(data.verification_methods(
+        None,
+        None,
+        None,
+        Some(&type_vm.fragment),
+    ))?;
+    let vm = data.find_verification_method(&type_vm.fragment);
+    require!(vm.is_some(), DidSolError::VmFragmentNotFound);
+    let vm = vm.unwrap();
+    let current_type =
+        VerificationMethodType::from_u8(vm.method_type).ok_or(DidSolError::ConversionError)?;
+    // the key data stays the same, flags are kept
+    require!(
+        current_type.is_compatible_with(&new_type),
+        DidSolError::VmIncompatibleType
+    );
+    vm.method_type = type_vm.method_type;
+
+    emit!(VmTypeChanged {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        fragment: type_vm.fragment,
+        method_type: type_vm.method_type,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetVmTypeMethod<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+}
+
+/// Argument
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
+pub struct UpdateTypeVerificationMethod {
+    pub fragment: String,
+    pub method_type: u8,
+}
@@ -0,0 +1,62 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION, DID_RECOVERY_SEED};
+use crate::errors::DidSolError;
+use crate::events::RecoveryStarted;
+use crate::state::{DidAccount, DidRecovery, VerificationMethod};
+use anchor_lang::prelude::*;
+
+pub fn start_recovery(
+    ctx: Context<StartRecovery>,
+    guardians: Vec<Pubkey>,
+    verification_method: VerificationMethod,
+) -> Result<()> {
+    let data = &ctx.accounts.did_data;
+    let policy = data
+        .recovery_policy
+        .as_ref()
+        .ok_or_else(|| error!(DidSolError::RecoveryNotEnabled))?;
+    // guardians start a recovery jointly
+    policy.check_guardians(&guardians, ctx.remaining_accounts)?;
+    // the verification method must be accepted by execute_recovery
+    data.check_recovery_verification_method(&verification_method)?;
+
+    let recovery = &mut ctx.accounts.did_recovery;
+    recovery.did_account = data.key();
+    recovery.bump = *ctx.bumps.get("did_recovery").unwrap();
+    recovery.payer = ctx.accounts.payer.key();
+    recovery.verification_method = verification_method;
+    recovery.approvals = guardians.clone();
+    recovery.start(policy, Clock::get()?.unix_timestamp);
+
+    emit!(RecoveryStarted {
+        did: data.initial_authority,
+        guardians,
+        nonce: data.nonce,
+        verification_method: recovery.verification_method.clone(),
+    });
+
+    Ok(())
+}
+
+/// The DID account and a signing authority of every guardian are passed as remaining accounts,
+/// in the order of `guardians`.
+#[derive(Accounts)]
+#[instruction(guardians: Vec<Pubkey>, verification_method: VerificationMethod)]
+pub struct StartRecovery<'info> {
+    #[account(
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DidRecovery::size(&verification_method, did_data.recovery_policy.as_ref().map_or(0, |policy| policy.guardians.len())),
+        seeds = [DID_RECOVERY_SEED.as_bytes(), did_data.key().as_ref()],
+        bump,
+    )]
+    pub did_recovery: Account<'info, DidRecovery>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
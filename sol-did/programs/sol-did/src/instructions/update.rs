@@ -1,5 +1,8 @@
+use crate::constants::DID_ACCOUNT_VERSION;
+use crate::eip712;
 use crate::errors::DidSolError;
-use crate::state::{DidAccount, Secp256k1RawSignature};
+use crate::events::DidUpdated;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature};
 use crate::{Service, VerificationMethod};
 use anchor_lang::prelude::*;
 
@@ -10,9 +13,21 @@ pub fn update(
 ) -> Result<()> {
     // Move the business logic DidAccount struct.
     let data = &mut ctx.accounts.did_data;
-    if eth_signature.is_some() {
-        data.nonce += 1;
-    }
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "update",
+        &update_arg.try_to_vec().unwrap(),
+        Some(eip712::update(
+            &update_arg.verification_methods,
+            &update_arg.services,
+            &update_arg.native_controllers,
+            &update_arg.other_controllers,
+        )),
+        eth_signature.as_ref(),
+        None,
+        InstructionPermissions::UPDATE,
+        ctx.remaining_accounts,
+    )?;
 
     // Cannot update DID if protected services exist.
     require!(
@@ -20,6 +35,13 @@ pub fn update(
         DidSolError::VmCannotRemoveProtected
     );
 
+    // the authority can neither replace nor grant permissions it does not have
+    authority.check_can_manage(
+        data.verification_methods
+            .iter()
+            .chain(update_arg.verification_methods.iter()),
+    )?;
+
     data.set_services(update_arg.services, false)?;
     data.set_verification_methods(Vec::new(), update_arg.verification_methods)?;
     data.set_native_controllers(update_arg.native_controllers)?;
@@ -31,17 +53,22 @@ pub fn update(
         DidSolError::VmCannotRemoveLastAuthority
     );
 
+    emit!(DidUpdated {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+    });
+
     Ok(())
 }
 
 #[derive(Accounts)]
-#[instruction(update_arg: UpdateArg, eth_signature: Option<Secp256k1RawSignature>)]
 pub struct Update<'info> {
     #[account(
         mut,
-        seeds = [b"did-account", did_data.initial_verification_method.key_data.as_ref()],
+        seeds = [b"did-account", did_data.initial_authority.as_ref()],
         bump = did_data.bump,
-        constraint = did_data.find_authority_constraint(&authority.key(), &update_arg.try_to_vec().unwrap(), eth_signature.as_ref(), None).is_some()
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
     )]
     pub did_data: Account<'info, DidAccount>,
     pub authority: Signer<'info>,
@@ -0,0 +1,77 @@
+use crate::constants::{DID_ACCOUNT_SEED, DID_ACCOUNT_VERSION};
+use crate::eip712;
+use crate::errors::DidSolError;
+use crate::events::ServiceUpdated;
+use crate::state::{DidAccount, InstructionPermissions, Secp256k1RawSignature, ServiceEndpoint};
+use crate::utils::realloc_did_account;
+use anchor_lang::prelude::*;
+
+pub fn update_service<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpdateService<'info>>,
+    update_service_arg: UpdateServiceArg,
+    eth_signature: Option<Secp256k1RawSignature>,
+) -> Result<()> {
+    let data = &mut ctx.accounts.did_data;
+    let authority = data.authorize_update(
+        &ctx.accounts.authority.key(),
+        "update_service",
+        &update_service_arg.try_to_vec().unwrap(),
+        Some(eip712::update_service(
+            &update_service_arg.fragment,
+            update_service_arg.service_types.as_deref(),
+            update_service_arg.service_endpoint.as_ref(),
+        )),
+        eth_signature.as_ref(),
+        None,
+        // updating a service is a restricted overwrite with add_service
+        InstructionPermissions::ADD_SERVICE,
+        ctx.remaining_accounts,
+    )?;
+
+    data.update_service(
+        &update_service_arg.fragment,
+        update_service_arg.service_types,
+        update_service_arg.service_endpoint,
+    )?;
+
+    realloc_did_account(
+        &data.to_account_info(),
+        8 + data.size(),
+        ctx.accounts.payer.as_ref(),
+        ctx.accounts.system_program.as_ref(),
+    )?;
+
+    emit!(ServiceUpdated {
+        did: data.initial_authority,
+        authority_fragment: authority.fragment,
+        nonce: data.nonce,
+        fragment: update_service_arg.fragment,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateService<'info> {
+    #[account(
+        mut,
+        seeds = [DID_ACCOUNT_SEED.as_bytes(), did_data.initial_authority.as_ref()],
+        bump = did_data.bump,
+        constraint = did_data.version == DID_ACCOUNT_VERSION @ DidSolError::UnsupportedAccountVersion,
+    )]
+    pub did_data: Account<'info, DidAccount>,
+    pub authority: Signer<'info>,
+    /// Pays the rent if the DID account grows, receives the rent freed if it shrinks (optional)
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    pub system_program: Option<Program<'info, System>>,
+}
+
+/// Argument
+/// The fields of the service `fragment` to replace, fields that are `None` are kept.
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
+pub struct UpdateServiceArg {
+    pub fragment: String,
+    pub service_types: Option<Vec<String>>,
+    pub service_endpoint: Option<ServiceEndpoint>,
+}
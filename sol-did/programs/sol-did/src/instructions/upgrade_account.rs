@@ -0,0 +1,62 @@
+use crate::constants::DID_ACCOUNT_VERSION;
+use crate::errors::DidSolError;
+use crate::events::DidUpgraded;
+use crate::state::DidAccount;
+use crate::utils::derive_did_account_with_bump;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+/// Rewrites a DidAccount with a previous layout version into the current layout.
+/// The content of the DID is not changed, therefore anyone can upgrade an account.
+pub fn upgrade_account(ctx: Context<UpgradeAccount>) -> Result<()> {
+    let did_info = ctx.accounts.did_data.to_account_info();
+
+    let data = {
+        let account_data = did_info.try_borrow_data()?;
+        require!(
+            account_data.get(8) != Some(&DID_ACCOUNT_VERSION),
+            DidSolError::UnsupportedAccountVersion
+        );
+        DidAccount::try_from_account_data(&account_data)?
+    };
+
+    let address = derive_did_account_with_bump(&data.initial_authority.to_bytes(), data.bump)?;
+    require_keys_eq!(address, did_info.key(), ErrorCode::ConstraintSeeds);
+
+    let new_size = std::cmp::max(did_info.data_len(), 8 + data.size());
+    let new_rent_minimum = Rent::get()?.minimum_balance(new_size);
+    if new_rent_minimum > did_info.lamports() {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: did_info.clone(),
+                },
+            ),
+            new_rent_minimum - did_info.lamports(),
+        )?;
+    }
+    did_info.realloc(new_size, false)?;
+
+    let mut account_data = did_info.try_borrow_mut_data()?;
+    data.try_serialize(&mut &mut account_data[..])?;
+
+    emit!(DidUpgraded {
+        did: data.initial_authority,
+        version: data.version,
+        nonce: data.nonce,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpgradeAccount<'info> {
+    /// CHECK: The account is checked to be a DidAccount with a previous version in the instruction
+    #[account(mut, owner = crate::ID)]
+    pub did_data: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
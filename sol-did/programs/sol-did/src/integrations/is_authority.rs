@@ -1,4 +1,5 @@
-use crate::state::VerificationMethodType;
+use crate::constants::DID_ACCOUNT_VERSION;
+use crate::state::{DidAccountView, VerificationMethodType};
 pub use crate::utils::{derive_did_account, derive_did_account_with_bump};
 use crate::{errors::DidSolError, DidAccount};
 use anchor_lang::prelude::*;
@@ -21,7 +22,6 @@ pub fn is_authority(
     filter_types: Option<&[VerificationMethodType]>,
     filter_fragment: Option<&String>,
 ) -> Result<bool> {
-    // TODO: Consider to replace this with DidAccount::try_from(...)
     if did_account.owner == &System::id() {
         // msg!("Validating generative DID");
         // the DID is a generative DID - the only authority is the key itself
@@ -39,7 +39,18 @@ pub fn is_authority(
         return Ok(*did_account.key == address);
     }
 
-    let did_data: Account<DidAccount> = Account::try_from(did_account)?;
+    if controlling_did_accounts.is_empty()
+        && did_account.try_borrow_data()?.get(8) == Some(&DID_ACCOUNT_VERSION)
+    {
+        // direct authority on a current account: the verification methods are read in place
+        let did_data = DidAccountView::try_from_account_info(did_account)?;
+        return Ok(did_data
+            .find_authority(key, filter_types, filter_fragment)?
+            .is_some());
+    }
+
+    // accepts all supported account versions
+    let did_data = DidAccount::try_from_account_info(did_account)?;
 
     // if a chain of controlling did accounts was provided,
     // validate them by parsing and checking the controller relationship,
@@ -73,12 +84,25 @@ pub fn is_authority(
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::constants::VM_DEFAULT_FRAGMENT_NAME;
-    use crate::state::{DidAccount, VerificationMethodFlags};
-    use crate::utils::derive_did_account;
-    use crate::{id, VerificationMethod};
-    use std::cell::RefCell;
-    use std::rc::Rc;
+    use crate::constants::{DID_ACCOUNT_VERSION, VM_DEFAULT_FRAGMENT_NAME};
+    use crate::eip712;
+    use crate::legacy::{DidAccountV0, VerificationMethodV0};
+    use crate::state::{
+        DidAccount, InstructionPermissions, UpdateAuthority, VerificationMethodFlags,
+    };
+    use crate::test_utils::{
+        create_instructions_sysvar_data, create_precompile_instruction_data,
+        create_test_account_info, eth_sign_hash,
+    };
+    use crate::utils::{convert_secp256k1pub_key_to_address, derive_did_account};
+    use crate::VerificationMethod;
+    use anchor_lang::Discriminator;
+    use solana_program::ed25519_program;
+    use solana_program::keccak;
+    use solana_program::secp256k1_recover::Secp256k1Pubkey;
+    use solana_program::system_program;
+    use solana_program::sysvar;
+    use solana_program::sysvar::instructions;
     use std::str::FromStr;
 
     fn create_test_authority() -> Pubkey {
@@ -87,19 +111,33 @@ mod test {
 
     fn create_test_did(test_authority: Pubkey) -> DidAccount {
         DidAccount {
-            version: 0,
+            version: DID_ACCOUNT_VERSION,
             bump: 0,
             nonce: 0,
+            initial_authority: test_authority,
             initial_verification_method: VerificationMethod {
                 fragment: VM_DEFAULT_FRAGMENT_NAME.to_string(),
                 flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
                 method_type: 0,
                 key_data: test_authority.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
             },
             verification_methods: vec![],
             services: vec![],
             native_controllers: vec![],
             other_controllers: vec![],
+            also_known_as: vec![],
+            contexts: vec![],
+            authority_threshold: 1,
+            recovery_policy: None,
+            deactivated: false,
+            update_count: 0,
+            created_slot: 0,
+            created_at: 0,
+            updated_slot: 0,
+            updated_at: 0,
         }
     }
 
@@ -138,16 +176,8 @@ mod test {
         let derived_did_account = derive_did_account(&test_authority.to_bytes());
 
         let mut lamports = 1;
-        let account_info = AccountInfo {
-            key: &derived_did_account.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut lamports)),
-            data: Rc::new(RefCell::new(&mut data)),
-            owner: &id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let account_info =
+            create_test_account_info(&derived_did_account.0, &crate::ID, &mut lamports, &mut data);
 
         let should_be_true = is_authority(
             &account_info,
@@ -180,16 +210,12 @@ mod test {
         let mut lamports = 1; // account can have a balance
         let derived_did_account = derive_did_account(&test_authority.to_bytes());
 
-        let account_info = AccountInfo {
-            key: &derived_did_account.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut lamports)),
-            data: Rc::new(RefCell::new(&mut data)),
-            owner: &System::id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let account_info = create_test_account_info(
+            &derived_did_account.0,
+            &system_program::ID,
+            &mut lamports,
+            &mut data,
+        );
 
         let should_be_true = is_authority(
             &account_info,
@@ -210,16 +236,12 @@ mod test {
 
         let mut data: Vec<u8> = Vec::with_capacity(0);
         let mut lamports = 0; // empty account
-        let account_info = AccountInfo {
-            key: &invalid_did_account_address,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut lamports)),
-            data: Rc::new(RefCell::new(&mut data)),
-            owner: &System::id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let account_info = create_test_account_info(
+            &invalid_did_account_address,
+            &system_program::ID,
+            &mut lamports,
+            &mut data,
+        );
 
         // either this returns Ok(false) or Error, but NOT Ok(true).
         let should_be_false = is_authority(
@@ -246,16 +268,8 @@ mod test {
         let derived_did_account = derive_did_account(&test_authority.to_bytes());
 
         let mut lamports = 1;
-        let account_info = AccountInfo {
-            key: &derived_did_account.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut lamports)),
-            data: Rc::new(RefCell::new(&mut data)),
-            owner: &id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let account_info =
+            create_test_account_info(&derived_did_account.0, &crate::ID, &mut lamports, &mut data);
 
         let should_be_false = is_authority(
             &account_info,
@@ -282,6 +296,9 @@ mod test {
                 flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
                 method_type: 0,
                 key_data: some_other_authority.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
             });
 
         let mut data: Vec<u8> = Vec::with_capacity(1024);
@@ -289,16 +306,8 @@ mod test {
         let derived_did_account = derive_did_account(&test_authority.to_bytes());
 
         let mut lamports = 1;
-        let account_info = AccountInfo {
-            key: &derived_did_account.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut lamports)),
-            data: Rc::new(RefCell::new(&mut data)),
-            owner: &id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let account_info =
+            create_test_account_info(&derived_did_account.0, &crate::ID, &mut lamports, &mut data);
 
         let should_be_true = is_authority(
             &account_info,
@@ -325,6 +334,9 @@ mod test {
                 flags: VerificationMethodFlags::AUTHENTICATION.bits(), // not CAPABILITY_INVOCATION
                 method_type: 0,
                 key_data: some_other_authority.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
             });
 
         let mut data: Vec<u8> = Vec::with_capacity(1024);
@@ -333,16 +345,8 @@ mod test {
         let mut lamports = 1;
         let derived_did_account = derive_did_account(&test_authority.to_bytes());
 
-        let account_info = AccountInfo {
-            key: &derived_did_account.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut lamports)),
-            data: Rc::new(RefCell::new(&mut data)),
-            owner: &id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let account_info =
+            create_test_account_info(&derived_did_account.0, &crate::ID, &mut lamports, &mut data);
 
         let should_be_false = is_authority(
             &account_info,
@@ -356,6 +360,148 @@ mod test {
         assert!(!should_be_false);
     }
 
+    #[test]
+    fn test_is_authority_accepts_all_ed25519_types() {
+        let test_authority = create_test_authority();
+        let derived_did_account = derive_did_account(&test_authority.to_bytes());
+
+        for method_type in VerificationMethodType::ed25519_types() {
+            let mut test_did_account = create_test_did(test_authority);
+            test_did_account.initial_verification_method.method_type = method_type as u8;
+
+            let mut data: Vec<u8> = Vec::with_capacity(1024);
+            test_did_account.try_serialize(&mut data).unwrap();
+
+            let mut lamports = 1;
+            let account_info = create_test_account_info(
+                &derived_did_account.0,
+                &crate::ID,
+                &mut lamports,
+                &mut data,
+            );
+
+            let should_be_true = is_authority(
+                &account_info,
+                Some(derived_did_account.1),
+                &[],
+                &test_authority.to_bytes(),
+                Some(&[VerificationMethodType::Ed25519VerificationKey2018]),
+                None,
+            )
+            .unwrap();
+            assert!(should_be_true);
+        }
+    }
+
+    #[test]
+    fn test_is_authority_fails_for_key_agreement_keys() {
+        let test_authority = create_test_authority();
+        let key_agreement_key = create_test_authority();
+
+        let mut test_did_account = create_test_did(test_authority);
+        test_did_account
+            .verification_methods
+            .push(VerificationMethod {
+                fragment: "key_agreement".to_string(),
+                // even if (wrongly) flagged as CAPABILITY_INVOCATION
+                flags: (VerificationMethodFlags::KEY_AGREEMENT
+                    | VerificationMethodFlags::CAPABILITY_INVOCATION)
+                    .bits(),
+                method_type: VerificationMethodType::X25519KeyAgreementKey2020 as u8,
+                key_data: key_agreement_key.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
+            });
+
+        let mut data: Vec<u8> = Vec::with_capacity(1024);
+        test_did_account.try_serialize(&mut data).unwrap();
+
+        let mut lamports = 1;
+        let derived_did_account = derive_did_account(&test_authority.to_bytes());
+
+        let account_info =
+            create_test_account_info(&derived_did_account.0, &crate::ID, &mut lamports, &mut data);
+
+        let should_be_false = is_authority(
+            &account_info,
+            Some(derived_did_account.1),
+            &[],
+            &key_agreement_key.to_bytes(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(!should_be_false);
+    }
+
+    #[test]
+    fn test_is_authority_fails_for_scoped_keys() {
+        let test_authority = create_test_authority();
+        let scoped_authority = create_test_authority();
+
+        let mut test_did_account = create_test_did(test_authority);
+        let scoped_vm = VerificationMethod {
+            fragment: "scoped_key".to_string(),
+            flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+            method_type: VerificationMethodType::Ed25519VerificationKey2018 as u8,
+            key_data: scoped_authority.to_bytes().to_vec(),
+            valid_from: None,
+            valid_until: None,
+            permissions: Some(
+                (InstructionPermissions::ADD_SERVICE | InstructionPermissions::REMOVE_SERVICE)
+                    .bits(),
+            ),
+        };
+        test_did_account
+            .verification_methods
+            .push(scoped_vm.clone());
+
+        // the scoped key can only authorize the instructions it is permitted
+        let scoped_key = scoped_authority.to_bytes();
+        assert!(test_did_account
+            .find_authority_with_permission(
+                &scoped_key,
+                None,
+                None,
+                InstructionPermissions::ADD_SERVICE
+            )
+            .is_some());
+        assert!(test_did_account
+            .find_authority_with_permission(&scoped_key, None, None, InstructionPermissions::CLOSE)
+            .is_none());
+
+        // and cannot manage verification methods with more permissions
+        let scoped_update_authority = UpdateAuthority::from(&scoped_vm);
+        assert!(scoped_update_authority
+            .check_can_manage([&scoped_vm])
+            .is_ok());
+        assert!(scoped_update_authority
+            .check_can_manage([&test_did_account.initial_verification_method])
+            .is_err());
+
+        let mut data: Vec<u8> = Vec::with_capacity(1024);
+        test_did_account.try_serialize(&mut data).unwrap();
+
+        let mut lamports = 1;
+        let derived_did_account = derive_did_account(&test_authority.to_bytes());
+
+        let account_info =
+            create_test_account_info(&derived_did_account.0, &crate::ID, &mut lamports, &mut data);
+
+        // scoped keys are not full authorities of the DID
+        let should_be_false = is_authority(
+            &account_info,
+            Some(derived_did_account.1),
+            &[],
+            &scoped_key,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(!should_be_false);
+    }
+
     #[test]
     fn test_is_authority_with_non_generative_controller() {
         let controller_authority = create_test_authority();
@@ -384,28 +530,20 @@ mod test {
             .unwrap();
 
         let mut controlled_lamports = 1;
-        let controlled_account_info = AccountInfo {
-            key: &controlled_did_account_address.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut controlled_lamports)),
-            data: Rc::new(RefCell::new(&mut controlled_data)),
-            owner: &id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let controlled_account_info = create_test_account_info(
+            &controlled_did_account_address.0,
+            &crate::ID,
+            &mut controlled_lamports,
+            &mut controlled_data,
+        );
 
         let mut controller_lamports = 1;
-        let controller_account_info = AccountInfo {
-            key: &controller_did_account_address.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut controller_lamports)),
-            data: Rc::new(RefCell::new(&mut controller_data)),
-            owner: &id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let controller_account_info = create_test_account_info(
+            &controller_did_account_address.0,
+            &crate::ID,
+            &mut controller_lamports,
+            &mut controller_data,
+        );
 
         // check if the controller authority is an authority on the controlled did
         let should_be_true = is_authority(
@@ -444,28 +582,20 @@ mod test {
             .unwrap();
 
         let mut controlled_lamports = 1;
-        let controlled_account_info = AccountInfo {
-            key: &controlled_did_account_address.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut controlled_lamports)),
-            data: Rc::new(RefCell::new(&mut controlled_data)),
-            owner: &id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let controlled_account_info = create_test_account_info(
+            &controlled_did_account_address.0,
+            &crate::ID,
+            &mut controlled_lamports,
+            &mut controlled_data,
+        );
 
         let mut controller_lamports = 1;
-        let generative_controller_account_info = AccountInfo {
-            key: &controller_did_account_address.0,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut controller_lamports)),
-            data: Rc::new(RefCell::new(&mut controller_data)),
-            owner: &System::id(),
-            executable: false,
-            rent_epoch: 0,
-        };
+        let generative_controller_account_info = create_test_account_info(
+            &controller_did_account_address.0,
+            &system_program::ID,
+            &mut controller_lamports,
+            &mut controller_data,
+        );
 
         // check if the controller authority is an authority on the controlled did
         let should_be_true = is_authority(
@@ -479,4 +609,367 @@ mod test {
         .unwrap();
         assert!(should_be_true);
     }
+
+    #[test]
+    fn test_is_authority_after_initial_authority_rotation() {
+        let controller_authority = create_test_authority();
+        let rotated_authority = create_test_authority();
+        let controlled_authority = create_test_authority();
+
+        let mut controller_did_account = create_test_did(controller_authority);
+        controller_did_account.rotate_initial_authority(&rotated_authority);
+        let mut controlled_did_account = create_test_did(controlled_authority);
+
+        let controller_did_account_address = derive_did_account(&controller_authority.to_bytes());
+        let controlled_did_account_address = derive_did_account(&controlled_authority.to_bytes());
+
+        // the controller is still referenced by its (unchanged) identifier
+        controlled_did_account
+            .set_native_controllers(vec![controller_authority])
+            .unwrap();
+
+        let mut controller_data: Vec<u8> = Vec::with_capacity(1024);
+        controller_did_account
+            .try_serialize(&mut controller_data)
+            .unwrap();
+
+        let mut controlled_data: Vec<u8> = Vec::with_capacity(1024);
+        controlled_did_account
+            .try_serialize(&mut controlled_data)
+            .unwrap();
+
+        let mut controlled_lamports = 1;
+        let controlled_account_info = create_test_account_info(
+            &controlled_did_account_address.0,
+            &crate::ID,
+            &mut controlled_lamports,
+            &mut controlled_data,
+        );
+
+        let mut controller_lamports = 1;
+        let controller_account_info = create_test_account_info(
+            &controller_did_account_address.0,
+            &crate::ID,
+            &mut controller_lamports,
+            &mut controller_data,
+        );
+
+        // the rotated key is an authority on the controller did
+        let should_be_true = is_authority(
+            &controller_account_info,
+            Some(controller_did_account_address.1),
+            &[],
+            &rotated_authority.to_bytes(),
+            Some(&[VerificationMethodType::Ed25519VerificationKey2018]),
+            None,
+        )
+        .unwrap();
+        assert!(should_be_true);
+
+        // the original key is not
+        let should_be_false = is_authority(
+            &controller_account_info,
+            Some(controller_did_account_address.1),
+            &[],
+            &controller_authority.to_bytes(),
+            Some(&[VerificationMethodType::Ed25519VerificationKey2018]),
+            None,
+        )
+        .unwrap();
+        assert!(!should_be_false);
+
+        // the rotated key is an authority on the controlled did via the controller chain
+        let should_be_true = is_authority(
+            &controlled_account_info,
+            Some(controlled_did_account_address.1),
+            &[(&controller_account_info, controller_authority)],
+            &rotated_authority.to_bytes(),
+            Some(&[VerificationMethodType::Ed25519VerificationKey2018]),
+            None,
+        )
+        .unwrap();
+        assert!(should_be_true);
+    }
+
+    #[test]
+    fn test_is_authority_ignores_validity_windows_without_clock() {
+        let test_authority = create_test_authority();
+        let expired_authority = create_test_authority();
+        let valid_authority = create_test_authority();
+        let future_authority = create_test_authority();
+
+        let mut test_did_account = create_test_did(test_authority);
+        test_did_account.verification_methods = vec![
+            VerificationMethod {
+                fragment: "expired_key".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: expired_authority.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: Some(1_000_000),
+                permissions: None,
+            },
+            VerificationMethod {
+                fragment: "valid_key".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: valid_authority.to_bytes().to_vec(),
+                valid_from: Some(999_999),
+                valid_until: Some(1_000_001),
+                permissions: None,
+            },
+            VerificationMethod {
+                fragment: "future_key".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: future_authority.to_bytes().to_vec(),
+                valid_from: Some(1_000_001),
+                valid_until: None,
+                permissions: None,
+            },
+        ];
+
+        let mut data: Vec<u8> = Vec::with_capacity(1024);
+        test_did_account.try_serialize(&mut data).unwrap();
+        let derived_did_account = derive_did_account(&test_authority.to_bytes());
+
+        let mut lamports = 1;
+        let account_info =
+            create_test_account_info(&derived_did_account.0, &crate::ID, &mut lamports, &mut data);
+
+        let check = |key: &Pubkey| {
+            is_authority(
+                &account_info,
+                Some(derived_did_account.1),
+                &[],
+                &key.to_bytes(),
+                Some(&[VerificationMethodType::Ed25519VerificationKey2018]),
+                None,
+            )
+            .unwrap()
+        };
+
+        // the Clock sysvar is not available, so VMs with a validity window are never an authority
+        // (see test_verification_method_validity_window for the window itself)
+        assert!(check(&test_authority));
+        assert!(!check(&valid_authority));
+        assert!(!check(&expired_authority));
+        assert!(!check(&future_authority));
+    }
+
+    #[test]
+    fn test_is_authority_fails_for_deactivated_did() {
+        let test_authority = create_test_authority();
+        let mut test_did_account = create_test_did(test_authority);
+        test_did_account.deactivate();
+
+        let mut data: Vec<u8> = Vec::with_capacity(1024);
+        test_did_account.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + test_did_account.tombstone_size());
+        let derived_did_account = derive_did_account(&test_authority.to_bytes());
+
+        let mut lamports = 1;
+        let account_info =
+            create_test_account_info(&derived_did_account.0, &crate::ID, &mut lamports, &mut data);
+
+        let should_be_false = is_authority(
+            &account_info,
+            Some(derived_did_account.1),
+            &[],
+            &test_authority.to_bytes(),
+            Some(&[VerificationMethodType::Ed25519VerificationKey2018]),
+            None,
+        )
+        .unwrap();
+        assert!(!should_be_false);
+    }
+
+    /// Builds a native Ed25519 or secp256r1 program instruction with a single (unchecked) signature
+    #[test]
+    fn test_find_ed25519_offline_authority() {
+        let test_authority = create_test_authority();
+        let offline_authority = create_test_authority();
+        let mut test_did_account = create_test_did(test_authority);
+        test_did_account.nonce = 3;
+        test_did_account
+            .verification_methods
+            .push(VerificationMethod {
+                fragment: "offline-key".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: VerificationMethodType::Ed25519VerificationKey2018 as u8,
+                key_data: offline_authority.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
+            });
+
+        let message = b"message";
+        let signed_message = [message.as_ref(), 3u64.to_le_bytes().as_ref()].concat();
+        let ed25519_data =
+            create_precompile_instruction_data(&offline_authority.to_bytes(), &signed_message, &[]);
+        let mut sysvar_data = create_instructions_sysvar_data(&ed25519_program::ID, &ed25519_data);
+
+        let mut lamports = 1;
+        let instructions_sysvar = create_test_account_info(
+            &instructions::ID,
+            &sysvar::ID,
+            &mut lamports,
+            &mut sysvar_data,
+        );
+
+        let vm = test_did_account.find_ed25519_offline_authority(
+            message,
+            &instructions_sysvar,
+            None,
+            InstructionPermissions::UPDATE,
+        );
+        assert_eq!(vm.unwrap().fragment, "offline-key");
+
+        // the signature is bound to the message and the nonce
+        assert!(test_did_account
+            .find_ed25519_offline_authority(
+                b"other message",
+                &instructions_sysvar,
+                None,
+                InstructionPermissions::UPDATE
+            )
+            .is_none());
+        test_did_account.nonce = 4;
+        assert!(test_did_account
+            .find_ed25519_offline_authority(
+                message,
+                &instructions_sysvar,
+                None,
+                InstructionPermissions::UPDATE
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_authority_constraint_accepts_eth_message_and_typed_data() {
+        let test_authority = create_test_authority();
+        let mut test_did_account = create_test_did(test_authority);
+        test_did_account.nonce = 5;
+        // the transaction signer is not an authority of the DID
+        let other_authority = create_test_authority();
+
+        let secret_key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+        let public_key = libsecp256k1::PublicKey::from_secret_key(&secret_key);
+        let address = convert_secp256k1pub_key_to_address(&Secp256k1Pubkey::new(
+            &public_key.serialize()[1..],
+        ));
+        test_did_account
+            .verification_methods
+            .push(VerificationMethod {
+                fragment: "eth-address".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020 as u8,
+                key_data: address.to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
+            });
+
+        // personal message signature
+        let message = b"message";
+        let message_with_nonce = [message.as_ref(), 5u64.to_le_bytes().as_ref()].concat();
+        let personal_message_hash = keccak::hashv(&[
+            b"\x19Ethereum Signed Message:\n",
+            message_with_nonce.len().to_string().as_bytes(),
+            &message_with_nonce,
+        ])
+        .to_bytes();
+        let signature = eth_sign_hash(&secret_key, &personal_message_hash);
+        let vm = test_did_account.find_authority_constraint(
+            &other_authority,
+            message,
+            None,
+            Some(&signature),
+            None,
+            InstructionPermissions::RESIZE,
+        );
+        assert_eq!(vm.unwrap().fragment, "eth-address");
+
+        // EIP-712 typed data signature
+        let did = format!("did:sol:{}", test_authority);
+        let typed_data_hash = eip712::resize(1000).signing_hash(&did, 5);
+        let signature = eth_sign_hash(&secret_key, &typed_data_hash);
+        let vm = test_did_account.find_authority_constraint(
+            &other_authority,
+            message,
+            Some(&typed_data_hash),
+            Some(&signature),
+            None,
+            InstructionPermissions::RESIZE,
+        );
+        assert_eq!(vm.unwrap().fragment, "eth-address");
+
+        // the typed data is bound to the arguments and the nonce
+        for other_typed_data_hash in [
+            eip712::resize(1001).signing_hash(&did, 5),
+            eip712::resize(1000).signing_hash(&did, 6),
+        ] {
+            assert!(test_did_account
+                .find_authority_constraint(
+                    &other_authority,
+                    message,
+                    Some(&other_typed_data_hash),
+                    Some(&signature),
+                    None,
+                    InstructionPermissions::RESIZE,
+                )
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_is_authority_accepts_all_account_versions() {
+        let test_authority = create_test_authority();
+        let derived_did_account = derive_did_account(&test_authority.to_bytes());
+
+        let mut data_v0 = DidAccount::discriminator().to_vec();
+        DidAccountV0 {
+            version: 0,
+            bump: derived_did_account.1,
+            nonce: 0,
+            initial_verification_method: VerificationMethodV0 {
+                fragment: VM_DEFAULT_FRAGMENT_NAME.to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: test_authority.to_bytes().to_vec(),
+            },
+            verification_methods: vec![],
+            services: vec![],
+            native_controllers: vec![],
+            other_controllers: vec![],
+        }
+        .serialize(&mut data_v0)
+        .unwrap();
+        let mut data_latest: Vec<u8> = Vec::new();
+        create_test_did(test_authority)
+            .try_serialize(&mut data_latest)
+            .unwrap();
+        let mut data_unknown = data_latest.clone();
+        data_unknown[8] = DID_ACCOUNT_VERSION + 1;
+
+        for (mut data, supported) in [(data_v0, true), (data_latest, true), (data_unknown, false)] {
+            let mut lamports = 1;
+            let account_info = create_test_account_info(
+                &derived_did_account.0,
+                &crate::ID,
+                &mut lamports,
+                &mut data,
+            );
+
+            let result = is_authority(
+                &account_info,
+                None,
+                &[],
+                &test_authority.to_bytes(),
+                None,
+                None,
+            );
+            assert_eq!(result.ok(), supported.then_some(true));
+        }
+    }
 }
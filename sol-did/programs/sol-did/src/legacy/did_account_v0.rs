@@ -0,0 +1,185 @@
+use crate::errors::DidSolError;
+use crate::legacy::{DidAccountV1, VerificationMethodV1};
+use crate::state::DidAccount;
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Version 0 layout of a [`VerificationMethod`](crate::state::VerificationMethod)
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct VerificationMethodV0 {
+    pub fragment: String,
+    pub flags: u16,
+    pub method_type: u8,
+    pub key_data: Vec<u8>,
+}
+
+impl From<VerificationMethodV0> for VerificationMethodV1 {
+    fn from(vm: VerificationMethodV0) -> Self {
+        VerificationMethodV1 {
+            fragment: vm.fragment,
+            flags: vm.flags,
+            method_type: vm.method_type,
+            key_data: vm.key_data,
+            valid_from: None,
+            valid_until: None,
+        }
+    }
+}
+
+/// Version 0, 1 and 3 layout of a [`Service`](crate::state::Service)
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct ServiceV0 {
+    pub fragment: String,
+    pub service_type: String,
+    pub service_endpoint: String,
+}
+
+/// Version 0 layout of a [`DidAccount`], as created by sol-did <= 3.3.0
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct DidAccountV0 {
+    pub version: u8,
+    pub bump: u8,
+    pub nonce: u64,
+    pub initial_verification_method: VerificationMethodV0,
+    pub verification_methods: Vec<VerificationMethodV0>,
+    pub services: Vec<ServiceV0>,
+    pub native_controllers: Vec<Pubkey>,
+    pub other_controllers: Vec<String>,
+}
+
+impl DidAccountV0 {
+    /// Deserializes the account data of a version 0 DidAccount (including the discriminator).
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() > 8 && data[..8] == DidAccount::discriminator(),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let account = DidAccountV0::deserialize(&mut &data[8..])
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+        require!(account.version == 0, DidSolError::UnsupportedAccountVersion);
+
+        Ok(account)
+    }
+
+    /// The key the DID account address was derived from
+    pub fn initial_authority(&self) -> Result<Pubkey> {
+        <[u8; 32]>::try_from(self.initial_verification_method.key_data.as_slice())
+            .map(Pubkey::new_from_array)
+            .map_err(|_| error!(DidSolError::ConversionError))
+    }
+
+    /// Upgrades the account to the current layout.
+    pub fn upgrade(self) -> Result<DidAccount> {
+        DidAccountV1::try_from(self)?.upgrade()
+    }
+}
+
+impl TryFrom<DidAccountV0> for DidAccountV1 {
+    type Error = Error;
+
+    /// The metadata of version 0 accounts is unknown (0).
+    fn try_from(account: DidAccountV0) -> Result<Self> {
+        let initial_authority = account.initial_authority()?;
+
+        Ok(DidAccountV1 {
+            version: 1,
+            bump: account.bump,
+            nonce: account.nonce,
+            initial_authority,
+            initial_verification_method: account.initial_verification_method.into(),
+            verification_methods: account
+                .verification_methods
+                .into_iter()
+                .map(VerificationMethodV1::from)
+                .collect(),
+            services: account.services,
+            native_controllers: account.native_controllers,
+            other_controllers: account.other_controllers,
+            authority_threshold: 1,
+            recovery_policy: None,
+            deactivated: false,
+            update_count: 0,
+            created_slot: 0,
+            created_at: 0,
+            updated_slot: 0,
+            updated_at: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::{DID_ACCOUNT_VERSION, VM_DEFAULT_FRAGMENT_NAME};
+    use crate::state::{ServiceEndpoint, VerificationMethodFlags};
+
+    fn create_test_did_v0(authority: &Pubkey) -> DidAccountV0 {
+        DidAccountV0 {
+            version: 0,
+            bump: 255,
+            nonce: 3,
+            initial_verification_method: VerificationMethodV0 {
+                fragment: VM_DEFAULT_FRAGMENT_NAME.to_string(),
+                flags: (VerificationMethodFlags::CAPABILITY_INVOCATION
+                    | VerificationMethodFlags::PROTECTED)
+                    .bits(),
+                method_type: 0,
+                key_data: authority.to_bytes().to_vec(),
+            },
+            verification_methods: vec![VerificationMethodV0 {
+                fragment: "second_key".to_string(),
+                flags: VerificationMethodFlags::AUTHENTICATION.bits(),
+                method_type: 0,
+                key_data: Pubkey::new_unique().to_bytes().to_vec(),
+            }],
+            services: vec![ServiceV0 {
+                fragment: "service".to_string(),
+                service_type: "profile".to_string(),
+                service_endpoint: "https://example.com".to_string(),
+            }],
+            native_controllers: vec![Pubkey::new_unique()],
+            other_controllers: vec!["did:example:123".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_upgrade_v0_account() {
+        let authority = Pubkey::new_unique();
+        let mut account_data = DidAccount::discriminator().to_vec();
+        create_test_did_v0(&authority)
+            .serialize(&mut account_data)
+            .unwrap();
+
+        let legacy_data = DidAccountV0::try_from_account_data(&account_data).unwrap();
+        let upgraded = legacy_data.upgrade().unwrap();
+
+        assert_eq!(upgraded.version, DID_ACCOUNT_VERSION);
+        assert_eq!(upgraded.nonce, 3);
+        assert_eq!(upgraded.initial_authority, authority);
+        assert_eq!(
+            upgraded.initial_verification_method.flags,
+            (VerificationMethodFlags::CAPABILITY_INVOCATION | VerificationMethodFlags::PROTECTED)
+                .bits()
+        );
+        assert_eq!(upgraded.verification_methods[0].fragment, "second_key");
+        assert_eq!(upgraded.services[0].fragment, "service");
+        assert_eq!(
+            upgraded.services[0].service_types,
+            vec!["profile".to_string()]
+        );
+        assert_eq!(
+            upgraded.services[0].service_endpoint,
+            ServiceEndpoint::Uri("https://example.com".to_string())
+        );
+        assert_eq!(upgraded.native_controllers.len(), 1);
+        assert_eq!(upgraded.other_controllers.len(), 1);
+
+        let mut upgraded_data: Vec<u8> = Vec::new();
+        upgraded.try_serialize(&mut upgraded_data).unwrap();
+        assert_eq!(upgraded_data.len(), 8 + upgraded.size());
+
+        // an upgraded account cannot be upgraded again
+        assert!(DidAccountV0::try_from_account_data(&upgraded_data).is_err());
+    }
+}
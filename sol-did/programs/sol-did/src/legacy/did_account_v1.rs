@@ -0,0 +1,201 @@
+use crate::errors::DidSolError;
+use crate::legacy::{DidAccountV3, ServiceV0, VerificationMethodV3};
+use crate::state::{DidAccount, RecoveryPolicy};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Version 1 layout of a [`VerificationMethod`](crate::state::VerificationMethod), with a validity window
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct VerificationMethodV1 {
+    pub fragment: String,
+    pub flags: u16,
+    pub method_type: u8,
+    pub key_data: Vec<u8>,
+    pub valid_from: Option<i64>,
+    pub valid_until: Option<i64>,
+}
+
+impl From<VerificationMethodV1> for VerificationMethodV3 {
+    fn from(vm: VerificationMethodV1) -> Self {
+        VerificationMethodV3 {
+            fragment: vm.fragment,
+            flags: vm.flags,
+            method_type: vm.method_type,
+            key_data: vm.key_data,
+            valid_from: vm.valid_from,
+            valid_until: vm.valid_until,
+            permissions: None,
+        }
+    }
+}
+
+/// Version 1 to 3 layout of a [`RecoveryPolicy`]
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct RecoveryPolicyV1 {
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+    pub challenge_period: i64,
+}
+
+impl From<RecoveryPolicyV1> for RecoveryPolicy {
+    fn from(recovery_policy: RecoveryPolicyV1) -> Self {
+        RecoveryPolicy {
+            guardians: recovery_policy.guardians,
+            threshold: recovery_policy.threshold,
+            challenge_period: recovery_policy.challenge_period,
+        }
+    }
+}
+
+/// Version 1 layout of a [`DidAccount`], without verification method permissions
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct DidAccountV1 {
+    pub version: u8,
+    pub bump: u8,
+    pub nonce: u64,
+    pub initial_authority: Pubkey,
+    pub initial_verification_method: VerificationMethodV1,
+    pub verification_methods: Vec<VerificationMethodV1>,
+    pub services: Vec<ServiceV0>,
+    pub native_controllers: Vec<Pubkey>,
+    pub other_controllers: Vec<String>,
+    pub authority_threshold: u8,
+    pub recovery_policy: Option<RecoveryPolicyV1>,
+    pub deactivated: bool,
+    pub update_count: u64,
+    pub created_slot: u64,
+    pub created_at: i64,
+    pub updated_slot: u64,
+    pub updated_at: i64,
+}
+
+impl DidAccountV1 {
+    /// Deserializes the account data of a version 1 DidAccount (including the discriminator).
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() > 8 && data[..8] == DidAccount::discriminator(),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let account = DidAccountV1::deserialize(&mut &data[8..])
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+        require!(account.version == 1, DidSolError::UnsupportedAccountVersion);
+
+        Ok(account)
+    }
+
+    /// Upgrades the account to the current layout.
+    pub fn upgrade(self) -> Result<DidAccount> {
+        DidAccountV3::from(self).upgrade()
+    }
+}
+
+impl From<DidAccountV1> for DidAccountV3 {
+    /// Verification methods of version 1 accounts are unrestricted.
+    fn from(account: DidAccountV1) -> Self {
+        DidAccountV3 {
+            version: 3,
+            bump: account.bump,
+            nonce: account.nonce,
+            initial_authority: account.initial_authority,
+            initial_verification_method: account.initial_verification_method.into(),
+            verification_methods: account
+                .verification_methods
+                .into_iter()
+                .map(VerificationMethodV3::from)
+                .collect(),
+            services: account.services,
+            native_controllers: account.native_controllers,
+            other_controllers: account.other_controllers,
+            authority_threshold: account.authority_threshold,
+            recovery_policy: account.recovery_policy,
+            deactivated: account.deactivated,
+            update_count: account.update_count,
+            created_slot: account.created_slot,
+            created_at: account.created_at,
+            updated_slot: account.updated_slot,
+            updated_at: account.updated_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::DID_ACCOUNT_VERSION;
+    use crate::state::VerificationMethodFlags;
+
+    fn create_test_did_v1(authority: &Pubkey) -> DidAccountV1 {
+        DidAccountV1 {
+            version: 1,
+            bump: 255,
+            nonce: 3,
+            initial_authority: *authority,
+            initial_verification_method: VerificationMethodV1 {
+                fragment: "default".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: authority.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+            },
+            verification_methods: vec![VerificationMethodV1 {
+                fragment: "windowed".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: Pubkey::new_unique().to_bytes().to_vec(),
+                valid_from: Some(1_000),
+                valid_until: Some(2_000),
+            }],
+            services: vec![ServiceV0 {
+                fragment: "service".to_string(),
+                service_type: "profile".to_string(),
+                service_endpoint: "https://example.com".to_string(),
+            }],
+            native_controllers: vec![],
+            other_controllers: vec![],
+            authority_threshold: 1,
+            recovery_policy: Some(RecoveryPolicyV1 {
+                guardians: vec![Pubkey::new_unique()],
+                threshold: 1,
+                challenge_period: 86_400,
+            }),
+            deactivated: false,
+            update_count: 7,
+            created_slot: 10,
+            created_at: 1_000,
+            updated_slot: 20,
+            updated_at: 2_000,
+        }
+    }
+
+    #[test]
+    fn test_upgrade_v1_account() {
+        let authority = Pubkey::new_unique();
+        let mut account_data = DidAccount::discriminator().to_vec();
+        create_test_did_v1(&authority)
+            .serialize(&mut account_data)
+            .unwrap();
+
+        let legacy_data = DidAccountV1::try_from_account_data(&account_data).unwrap();
+        let upgraded = legacy_data.upgrade().unwrap();
+
+        assert_eq!(upgraded.version, DID_ACCOUNT_VERSION);
+        assert_eq!(upgraded.nonce, 3);
+        assert_eq!(upgraded.initial_authority, authority);
+        // verification methods of version 1 accounts are unrestricted
+        let vm = &upgraded.verification_methods[0];
+        assert_eq!(vm.fragment, "windowed");
+        assert_eq!((vm.valid_from, vm.valid_until), (Some(1_000), Some(2_000)));
+        assert_eq!(vm.permissions, None);
+        assert_eq!(upgraded.recovery_policy.as_ref().unwrap().threshold, 1);
+        assert_eq!(
+            upgraded.services[0].service_types,
+            vec!["profile".to_string()]
+        );
+        assert_eq!(upgraded.update_count, 7);
+
+        // version 1 accounts are not read with the version 3 layout
+        assert!(DidAccountV3::try_from_account_data(&account_data).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::constants::DID_ACCOUNT_VERSION;
+use crate::errors::DidSolError;
+use crate::legacy::{RecoveryPolicyV1, ServiceV0, VerificationMethodV3};
+use crate::state::{
+    DidAccount, RecoveryPolicy, Service, ServiceEndpoint, ServiceProperty, VerificationMethod,
+};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Version 2 layout of a [`ServiceEndpoint`]
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub enum ServiceEndpointV2 {
+    Uri(String),
+    Json(String),
+}
+
+impl From<ServiceEndpointV2> for ServiceEndpoint {
+    fn from(service_endpoint: ServiceEndpointV2) -> Self {
+        match service_endpoint {
+            ServiceEndpointV2::Uri(uri) => ServiceEndpoint::Uri(uri),
+            ServiceEndpointV2::Json(json) => ServiceEndpoint::Json(json),
+        }
+    }
+}
+
+/// Version 2 layout of a [`ServiceProperty`]
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct ServicePropertyV2 {
+    pub name: String,
+    pub value: String,
+}
+
+impl From<ServicePropertyV2> for ServiceProperty {
+    fn from(property: ServicePropertyV2) -> Self {
+        ServiceProperty {
+            name: property.name,
+            value: property.value,
+        }
+    }
+}
+
+/// Version 2 layout of a [`Service`], with multiple types, a URI or JSON endpoint and properties
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct ServiceV2 {
+    pub fragment: String,
+    pub service_types: Vec<String>,
+    pub service_endpoint: ServiceEndpointV2,
+    pub properties: Vec<ServicePropertyV2>,
+}
+
+impl From<ServiceV0> for ServiceV2 {
+    fn from(service: ServiceV0) -> Self {
+        ServiceV2 {
+            fragment: service.fragment,
+            service_types: vec![service.service_type],
+            service_endpoint: ServiceEndpointV2::Uri(service.service_endpoint),
+            properties: vec![],
+        }
+    }
+}
+
+impl From<ServiceV2> for Service {
+    fn from(service: ServiceV2) -> Self {
+        Service {
+            fragment: service.fragment,
+            service_types: service.service_types,
+            service_endpoint: service.service_endpoint.into(),
+            properties: service
+                .properties
+                .into_iter()
+                .map(ServiceProperty::from)
+                .collect(),
+        }
+    }
+}
+
+/// Version 2 layout of a [`DidAccount`], without `also_known_as` and `contexts`
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct DidAccountV2 {
+    pub version: u8,
+    pub bump: u8,
+    pub nonce: u64,
+    pub initial_authority: Pubkey,
+    pub initial_verification_method: VerificationMethodV3,
+    pub verification_methods: Vec<VerificationMethodV3>,
+    pub services: Vec<ServiceV2>,
+    pub native_controllers: Vec<Pubkey>,
+    pub other_controllers: Vec<String>,
+    pub authority_threshold: u8,
+    pub recovery_policy: Option<RecoveryPolicyV1>,
+    pub deactivated: bool,
+    pub update_count: u64,
+    pub created_slot: u64,
+    pub created_at: i64,
+    pub updated_slot: u64,
+    pub updated_at: i64,
+}
+
+impl DidAccountV2 {
+    /// Deserializes the account data of a version 2 DidAccount (including the discriminator).
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() > 8 && data[..8] == DidAccount::discriminator(),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let account = DidAccountV2::deserialize(&mut &data[8..])
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+        require!(account.version == 2, DidSolError::UnsupportedAccountVersion);
+
+        Ok(account)
+    }
+
+    /// Upgrades the account to the current layout.
+    pub fn upgrade(self) -> Result<DidAccount> {
+        Ok(DidAccount {
+            version: DID_ACCOUNT_VERSION,
+            bump: self.bump,
+            nonce: self.nonce,
+            initial_authority: self.initial_authority,
+            initial_verification_method: self.initial_verification_method.into(),
+            verification_methods: self
+                .verification_methods
+                .into_iter()
+                .map(VerificationMethod::from)
+                .collect(),
+            services: self.services.into_iter().map(Service::from).collect(),
+            native_controllers: self.native_controllers,
+            other_controllers: self.other_controllers,
+            also_known_as: vec![],
+            contexts: vec![],
+            authority_threshold: self.authority_threshold,
+            recovery_policy: self.recovery_policy.map(RecoveryPolicy::from),
+            deactivated: self.deactivated,
+            update_count: self.update_count,
+            created_slot: self.created_slot,
+            created_at: self.created_at,
+            updated_slot: self.updated_slot,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::VerificationMethodFlags;
+
+    fn create_test_did_v2(authority: &Pubkey) -> DidAccountV2 {
+        DidAccountV2 {
+            version: 2,
+            bump: 255,
+            nonce: 3,
+            initial_authority: *authority,
+            initial_verification_method: VerificationMethodV3 {
+                fragment: "default".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: authority.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
+            },
+            verification_methods: vec![],
+            services: vec![ServiceV2 {
+                fragment: "linked-domains".to_string(),
+                service_types: vec!["LinkedDomains".to_string()],
+                service_endpoint: ServiceEndpointV2::Json(
+                    r#"{"origins":["https://example.com"]}"#.to_string(),
+                ),
+                properties: vec![ServicePropertyV2 {
+                    name: "description".to_string(),
+                    value: r#""Domains""#.to_string(),
+                }],
+            }],
+            native_controllers: vec![],
+            other_controllers: vec![],
+            authority_threshold: 1,
+            recovery_policy: None,
+            deactivated: false,
+            update_count: 7,
+            created_slot: 10,
+            created_at: 1_000,
+            updated_slot: 20,
+            updated_at: 2_000,
+        }
+    }
+
+    #[test]
+    fn test_upgrade_v2_account() {
+        let authority = Pubkey::new_unique();
+        let mut account_data = DidAccount::discriminator().to_vec();
+        create_test_did_v2(&authority)
+            .serialize(&mut account_data)
+            .unwrap();
+
+        let legacy_data = DidAccountV2::try_from_account_data(&account_data).unwrap();
+        let upgraded = legacy_data.upgrade().unwrap();
+
+        assert_eq!(upgraded.version, DID_ACCOUNT_VERSION);
+        assert_eq!(upgraded.nonce, 3);
+        assert_eq!(upgraded.initial_authority, authority);
+        assert_eq!(
+            upgraded.services[0].service_endpoint,
+            ServiceEndpoint::Json(r#"{"origins":["https://example.com"]}"#.to_string())
+        );
+        assert_eq!(
+            upgraded.services[0].properties,
+            vec![ServiceProperty::string("description", "Domains")]
+        );
+        assert!(upgraded.also_known_as.is_empty());
+        assert!(upgraded.contexts.is_empty());
+        assert_eq!(upgraded.update_count, 7);
+
+        let mut upgraded_data: Vec<u8> = Vec::new();
+        upgraded.try_serialize(&mut upgraded_data).unwrap();
+        assert_eq!(upgraded_data.len(), 8 + upgraded.size());
+
+        // an upgraded account cannot be upgraded again
+        assert!(DidAccountV2::try_from_account_data(&upgraded_data).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::errors::DidSolError;
+use crate::legacy::{DidAccountV2, RecoveryPolicyV1, ServiceV0, ServiceV2};
+use crate::state::{DidAccount, VerificationMethod};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Version 2 and 3 layout of a [`VerificationMethod`], with permissions
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct VerificationMethodV3 {
+    pub fragment: String,
+    pub flags: u16,
+    pub method_type: u8,
+    pub key_data: Vec<u8>,
+    pub valid_from: Option<i64>,
+    pub valid_until: Option<i64>,
+    pub permissions: Option<u32>,
+}
+
+impl From<VerificationMethodV3> for VerificationMethod {
+    fn from(vm: VerificationMethodV3) -> Self {
+        VerificationMethod {
+            fragment: vm.fragment,
+            flags: vm.flags,
+            method_type: vm.method_type,
+            key_data: vm.key_data,
+            valid_from: vm.valid_from,
+            valid_until: vm.valid_until,
+            permissions: vm.permissions,
+        }
+    }
+}
+
+/// Version 3 layout of a [`DidAccount`], with verification method permissions and single type, URI
+/// services. It was introduced after version 2, version 2 accounts already have structured services.
+#[derive(Clone, BorshSerialize, BorshDeserialize)]
+pub struct DidAccountV3 {
+    pub version: u8,
+    pub bump: u8,
+    pub nonce: u64,
+    pub initial_authority: Pubkey,
+    pub initial_verification_method: VerificationMethodV3,
+    pub verification_methods: Vec<VerificationMethodV3>,
+    pub services: Vec<ServiceV0>,
+    pub native_controllers: Vec<Pubkey>,
+    pub other_controllers: Vec<String>,
+    pub authority_threshold: u8,
+    pub recovery_policy: Option<RecoveryPolicyV1>,
+    pub deactivated: bool,
+    pub update_count: u64,
+    pub created_slot: u64,
+    pub created_at: i64,
+    pub updated_slot: u64,
+    pub updated_at: i64,
+}
+
+impl DidAccountV3 {
+    /// Deserializes the account data of a version 3 DidAccount (including the discriminator).
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() > 8 && data[..8] == DidAccount::discriminator(),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let account = DidAccountV3::deserialize(&mut &data[8..])
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+        require!(account.version == 3, DidSolError::UnsupportedAccountVersion);
+
+        Ok(account)
+    }
+
+    /// Upgrades the account to the current layout.
+    pub fn upgrade(self) -> Result<DidAccount> {
+        DidAccountV2::from(self).upgrade()
+    }
+}
+
+impl From<DidAccountV3> for DidAccountV2 {
+    fn from(account: DidAccountV3) -> Self {
+        DidAccountV2 {
+            version: 2,
+            bump: account.bump,
+            nonce: account.nonce,
+            initial_authority: account.initial_authority,
+            initial_verification_method: account.initial_verification_method,
+            verification_methods: account.verification_methods,
+            services: account.services.into_iter().map(ServiceV2::from).collect(),
+            native_controllers: account.native_controllers,
+            other_controllers: account.other_controllers,
+            authority_threshold: account.authority_threshold,
+            recovery_policy: account.recovery_policy,
+            deactivated: account.deactivated,
+            update_count: account.update_count,
+            created_slot: account.created_slot,
+            created_at: account.created_at,
+            updated_slot: account.updated_slot,
+            updated_at: account.updated_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::DID_ACCOUNT_VERSION;
+    use crate::state::{ServiceEndpoint, VerificationMethodFlags};
+
+    fn create_test_did_v3(authority: &Pubkey) -> DidAccountV3 {
+        DidAccountV3 {
+            version: 3,
+            bump: 255,
+            nonce: 3,
+            initial_authority: *authority,
+            initial_verification_method: VerificationMethodV3 {
+                fragment: "default".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: authority.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
+            },
+            verification_methods: vec![VerificationMethodV3 {
+                fragment: "restricted".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: 0,
+                key_data: Pubkey::new_unique().to_bytes().to_vec(),
+                valid_from: Some(1_000),
+                valid_until: Some(2_000),
+                permissions: Some(1 << 8),
+            }],
+            services: vec![ServiceV0 {
+                fragment: "service".to_string(),
+                service_type: "profile".to_string(),
+                service_endpoint: "https://example.com".to_string(),
+            }],
+            native_controllers: vec![Pubkey::new_unique()],
+            other_controllers: vec![],
+            authority_threshold: 1,
+            recovery_policy: Some(RecoveryPolicyV1 {
+                guardians: vec![Pubkey::new_unique()],
+                threshold: 1,
+                challenge_period: 86_400,
+            }),
+            deactivated: false,
+            update_count: 7,
+            created_slot: 10,
+            created_at: 1_000,
+            updated_slot: 20,
+            updated_at: 2_000,
+        }
+    }
+
+    #[test]
+    fn test_upgrade_v3_account() {
+        let authority = Pubkey::new_unique();
+        let mut account_data = DidAccount::discriminator().to_vec();
+        create_test_did_v3(&authority)
+            .serialize(&mut account_data)
+            .unwrap();
+
+        let legacy_data = DidAccountV3::try_from_account_data(&account_data).unwrap();
+        let upgraded = legacy_data.upgrade().unwrap();
+
+        assert_eq!(upgraded.version, DID_ACCOUNT_VERSION);
+        assert_eq!(upgraded.nonce, 3);
+        assert_eq!(upgraded.initial_authority, authority);
+        let vm = &upgraded.verification_methods[0];
+        assert_eq!(vm.fragment, "restricted");
+        assert_eq!((vm.valid_from, vm.valid_until), (Some(1_000), Some(2_000)));
+        assert_eq!(vm.permissions, Some(1 << 8));
+        let recovery_policy = upgraded.recovery_policy.as_ref().unwrap();
+        assert_eq!(recovery_policy.threshold, 1);
+        assert_eq!(recovery_policy.challenge_period, 86_400);
+        assert_eq!(
+            upgraded.services[0].service_types,
+            vec!["profile".to_string()]
+        );
+        assert_eq!(
+            upgraded.services[0].service_endpoint,
+            ServiceEndpoint::Uri("https://example.com".to_string())
+        );
+        // the metadata of version 3 accounts is kept
+        assert_eq!(upgraded.update_count, 7);
+        assert_eq!(upgraded.created_at, 1_000);
+        assert_eq!(upgraded.updated_at, 2_000);
+
+        let mut upgraded_data: Vec<u8> = Vec::new();
+        upgraded.try_serialize(&mut upgraded_data).unwrap();
+        assert_eq!(upgraded_data.len(), 8 + upgraded.size());
+
+        // an upgraded account cannot be upgraded again
+        assert!(DidAccountV3::try_from_account_data(&upgraded_data).is_err());
+        // version 3 accounts are not read with the version 2 layout
+        assert!(DidAccountV2::try_from_account_data(&account_data).is_err());
+    }
+}
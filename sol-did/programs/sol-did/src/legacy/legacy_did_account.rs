@@ -1,5 +1,7 @@
 use crate::constants::VM_DEFAULT_FRAGMENT_NAME;
-use crate::state::{DidAccount, VerificationMethodFlags, VerificationMethodType};
+use crate::state::{
+    DidAccount, ServiceEndpoint, ServiceProperty, VerificationMethodFlags, VerificationMethodType,
+};
 use crate::{Service, VerificationMethod};
 use anchor_lang::prelude::*;
 use anchor_lang::{AccountDeserialize, AccountSerialize, Owner};
@@ -23,7 +25,20 @@ pub struct LegacyServiceEndpoint {
 
 impl LegacyServiceEndpoint {
     pub fn post_migration_size(&self) -> usize {
-        4 + self.id.len() + 4 + self.endpoint_type.len() + 4 + self.endpoint.len()
+        self.migrate().size()
+    }
+
+    fn migrate(&self) -> Service {
+        Service {
+            fragment: self.id.clone(),
+            service_types: vec![self.endpoint_type.clone()],
+            service_endpoint: ServiceEndpoint::Uri(self.endpoint.clone()),
+            properties: if self.description.is_empty() {
+                vec![]
+            } else {
+                vec![ServiceProperty::string("description", &self.description)]
+            },
+        }
     }
 }
 
@@ -45,6 +60,9 @@ impl LegacyVerificationMethod {
             + 2 // flags
             + 1 // method
             + 4 + 32 // ed25519 pubkey
+            + 1 // valid_from
+            + 1 // valid_until
+            + 1 // permissions
     }
 }
 
@@ -121,6 +139,9 @@ impl LegacyDidAccount {
                     .unwrap(),
                 flags: self.get_flags(&vm.id).bits(),
                 key_data: vm.pubkey.to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
             })
             .collect()
     }
@@ -155,15 +176,7 @@ impl LegacyDidAccount {
     fn migrate_services(&self) -> Vec<Service> {
         self.service
             .iter()
-            .map(|se| {
-                Service {
-                    fragment: se.id.clone(),
-                    service_type: se.endpoint_type.clone(),
-                    service_endpoint: se.endpoint.clone(),
-                    // TODO: Documentation NOTE, Descriptions will be dropped
-                    // service_description: se.description.clone(),
-                }
-            })
+            .map(LegacyServiceEndpoint::migrate)
             .collect()
     }
 }
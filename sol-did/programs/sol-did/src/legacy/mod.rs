@@ -1,3 +1,11 @@
+mod did_account_v0;
+mod did_account_v1;
+mod did_account_v2;
+mod did_account_v3;
 mod legacy_did_account;
 
+pub use did_account_v0::*;
+pub use did_account_v1::*;
+pub use did_account_v2::*;
+pub use did_account_v3::*;
 pub use legacy_did_account::*;
@@ -12,7 +12,9 @@ unused_imports,
 )]
 
 pub mod constants;
+pub mod eip712;
 pub mod errors;
+pub mod events;
 pub mod integrations;
 pub mod legacy;
 pub mod state;
@@ -20,12 +22,14 @@ pub mod utils;
 
 mod instructions;
 mod security_txt;
+#[cfg(test)]
+mod test_utils;
 
 use crate::constants::DID_ACCOUNT_SEED;
 use crate::state::DidAccount;
 use anchor_lang::prelude::*;
 use instructions::*;
-use state::{Secp256k1RawSignature, Service, VerificationMethod};
+use state::{RecoveryPolicy, Secp256k1RawSignature, Service, VerificationMethod};
 
 declare_id!("didso1Dpqpm4CsiCjzP766BGY89CAdD6ZBL68cRhFPc");
 
@@ -45,28 +49,42 @@ pub mod sol_did {
         instructions::resize(ctx, size, eth_signature)
     }
 
+    pub fn compact(
+        ctx: Context<Compact>,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::compact(ctx, eth_signature)
+    }
+
     pub fn close(ctx: Context<Close>, eth_signature: Option<Secp256k1RawSignature>) -> Result<()> {
         instructions::close(ctx, eth_signature)
     }
 
-    pub fn add_verification_method(
-        ctx: Context<AddVerificationMethod>,
+    pub fn deactivate(
+        ctx: Context<Deactivate>,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::deactivate(ctx, eth_signature)
+    }
+
+    pub fn add_verification_method<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddVerificationMethod<'info>>,
         verification_method: VerificationMethod,
         eth_signature: Option<Secp256k1RawSignature>,
     ) -> Result<()> {
         instructions::add_verification_method(ctx, verification_method, eth_signature)
     }
 
-    pub fn remove_verification_method(
-        ctx: Context<RemoveVerificationMethod>,
+    pub fn remove_verification_method<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveVerificationMethod<'info>>,
         fragment: String,
         eth_signature: Option<Secp256k1RawSignature>,
     ) -> Result<()> {
         instructions::remove_verification_method(ctx, fragment, eth_signature)
     }
 
-    pub fn add_service(
-        ctx: Context<AddService>,
+    pub fn add_service<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddService<'info>>,
         service: Service,
         allow_overwrite: bool,
         eth_signature: Option<Secp256k1RawSignature>,
@@ -74,14 +92,22 @@ pub mod sol_did {
         instructions::add_service(ctx, service, allow_overwrite, eth_signature)
     }
 
-    pub fn remove_service(
-        ctx: Context<RemoveService>,
+    pub fn remove_service<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveService<'info>>,
         fragment: String,
         eth_signature: Option<Secp256k1RawSignature>,
     ) -> Result<()> {
         instructions::remove_service(ctx, fragment, eth_signature)
     }
 
+    pub fn update_service<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdateService<'info>>,
+        update_service_arg: UpdateServiceArg,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::update_service(ctx, update_service_arg, eth_signature)
+    }
+
     pub fn set_vm_flags(
         ctx: Context<SetVmFlagsMethod>,
         flags_vm: UpdateFlagsVerificationMethod,
@@ -90,6 +116,30 @@ pub mod sol_did {
         instructions::set_vm_flags(ctx, flags_vm, eth_signature)
     }
 
+    pub fn set_vm_type(
+        ctx: Context<SetVmTypeMethod>,
+        type_vm: UpdateTypeVerificationMethod,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::set_vm_type(ctx, type_vm, eth_signature)
+    }
+
+    pub fn set_vm_key<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetVmKeyMethod<'info>>,
+        key_vm: UpdateKeyVerificationMethod,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::set_vm_key(ctx, key_vm, eth_signature)
+    }
+
+    pub fn set_authority_threshold(
+        ctx: Context<SetAuthorityThreshold>,
+        authority_threshold: u8,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::set_authority_threshold(ctx, authority_threshold, eth_signature)
+    }
+
     pub fn set_controllers(
         ctx: Context<SetControllers>,
         set_controllers_arg: SetControllersArg,
@@ -98,6 +148,38 @@ pub mod sol_did {
         instructions::set_controllers(ctx, set_controllers_arg, eth_signature)
     }
 
+    pub fn add_also_known_as<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddAlsoKnownAs<'info>>,
+        uri: String,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::add_also_known_as(ctx, uri, eth_signature)
+    }
+
+    pub fn remove_also_known_as<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveAlsoKnownAs<'info>>,
+        uri: String,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::remove_also_known_as(ctx, uri, eth_signature)
+    }
+
+    pub fn add_context<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddContext<'info>>,
+        uri: String,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::add_context(ctx, uri, eth_signature)
+    }
+
+    pub fn remove_context<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveContext<'info>>,
+        uri: String,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::remove_context(ctx, uri, eth_signature)
+    }
+
     pub fn update(
         ctx: Context<Update>,
         update_arg: UpdateArg,
@@ -106,7 +188,61 @@ pub mod sol_did {
         instructions::update(ctx, update_arg, eth_signature)
     }
 
+    pub fn batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, Batch<'info>>,
+        operations: Vec<BatchOperation>,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::batch(ctx, operations, eth_signature)
+    }
+
+    pub fn rotate_initial_authority(
+        ctx: Context<RotateInitialAuthority>,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::rotate_initial_authority(ctx, eth_signature)
+    }
+
+    pub fn set_recovery_policy(
+        ctx: Context<SetRecoveryPolicy>,
+        recovery_policy: Option<RecoveryPolicy>,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::set_recovery_policy(ctx, recovery_policy, eth_signature)
+    }
+
+    pub fn start_recovery(
+        ctx: Context<StartRecovery>,
+        guardians: Vec<Pubkey>,
+        verification_method: VerificationMethod,
+    ) -> Result<()> {
+        instructions::start_recovery(ctx, guardians, verification_method)
+    }
+
+    pub fn approve_recovery(ctx: Context<ApproveRecovery>, guardian: Pubkey) -> Result<()> {
+        instructions::approve_recovery(ctx, guardian)
+    }
+
+    pub fn cancel_recovery(
+        ctx: Context<CancelRecovery>,
+        eth_signature: Option<Secp256k1RawSignature>,
+    ) -> Result<()> {
+        instructions::cancel_recovery(ctx, eth_signature)
+    }
+
+    pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+        instructions::execute_recovery(ctx)
+    }
+
+    pub fn close_recovery(ctx: Context<CloseRecovery>) -> Result<()> {
+        instructions::close_recovery(ctx)
+    }
+
     pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
         instructions::migrate(ctx)
     }
+
+    pub fn upgrade_account(ctx: Context<UpgradeAccount>) -> Result<()> {
+        instructions::upgrade_account(ctx)
+    }
 }
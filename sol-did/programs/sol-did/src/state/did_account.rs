@@ -1,4 +1,6 @@
+use crate::eip712::Eip712Struct;
 use crate::errors::DidSolError;
+use crate::legacy::{DidAccountV0, DidAccountV1, DidAccountV2, DidAccountV3};
 use anchor_lang::prelude::*;
 use bitflags::bitflags;
 use itertools::Itertools;
@@ -6,11 +8,19 @@ use num_derive::*;
 use num_traits::*;
 use std::fmt::{Display, Formatter};
 
-use crate::constants::VM_DEFAULT_FRAGMENT_NAME;
+use crate::constants::{
+    DID_ACCOUNT_VERSION, DID_SOL_PREFIX, SECP256R1_PROGRAM_ID, VM_DEFAULT_FRAGMENT_NAME,
+};
+use crate::state::{RecoveryPolicy, VerificationMethodRef};
 use crate::utils::{
     check_other_controllers, convert_secp256k1pub_key_to_address, derive_did_account,
-    derive_did_account_with_bump, eth_verify_message,
+    derive_did_account_with_bump, eth_verify_message, eth_verify_typed_data, is_valid_uri,
+    json_string, preceding_instructions, precompile_verified_signatures, signed_update_message,
+    webauthn_rp_id_hash, webauthn_verify_assertion, ED25519_PUBKEY_SIZE,
+    SECP256R1_COMPRESSED_PUBKEY_SIZE, WEBAUTHN_KEY_DATA_SIZE,
 };
+use solana_program::secp256k1_recover::Secp256k1Pubkey;
+use solana_program::{ed25519_program, hash::hash, sysvar};
 
 #[account]
 pub struct DidAccount {
@@ -20,6 +30,9 @@ pub struct DidAccount {
     pub bump: u8,
     /// Nonce, for protecting against replay attacks around secp256k1 signatures.
     pub nonce: u64,
+    /// The authority the DID was created with. The DID identifier and the account address are
+    /// derived from this key, it does not change when the initial verification method is rotated.
+    pub initial_authority: Pubkey,
     /// The initial authority key, automatically being added to the array of all Verification Methods.
     pub initial_verification_method: VerificationMethod,
     /// All verification methods
@@ -30,6 +43,26 @@ pub struct DidAccount {
     pub native_controllers: Vec<Pubkey>,
     /// Controller (others) - all others
     pub other_controllers: Vec<String>,
+    /// Other identifiers of the subject, e.g. `did:web:example.com` (alsoKnownAs)
+    pub also_known_as: Vec<String>,
+    /// Additional JSON-LD contexts of the DID document (@context)
+    pub contexts: Vec<String>,
+    /// Number of distinct CAPABILITY_INVOCATION verification methods that need to sign an update
+    pub authority_threshold: u8,
+    /// Guardians that can jointly recover the DID (optional)
+    pub recovery_policy: Option<RecoveryPolicy>,
+    /// A deactivated DID is a tombstone without any authority. It cannot be reactivated.
+    pub deactivated: bool,
+    /// Number of updates of the DID since its creation (or upgrade)
+    pub update_count: u64,
+    /// Slot the DID was created at (0 if unknown)
+    pub created_slot: u64,
+    /// Unix timestamp the DID was created at (0 if unknown)
+    pub created_at: i64,
+    /// Slot of the last update (0 if never updated)
+    pub updated_slot: u64,
+    /// Unix timestamp of the last update (0 if never updated)
+    pub updated_at: i64,
 }
 
 impl Display for DidAccount {
@@ -42,19 +75,33 @@ impl Display for DidAccount {
 impl Default for DidAccount {
     fn default() -> Self {
         DidAccount {
-            version: 0,
+            version: DID_ACCOUNT_VERSION,
             bump: 0,
             nonce: 0,
+            initial_authority: Pubkey::default(),
             initial_verification_method: VerificationMethod {
                 fragment: VM_DEFAULT_FRAGMENT_NAME.to_string(),
                 flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
                 method_type: 0,
                 key_data: vec![],
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
             },
             verification_methods: vec![],
             services: vec![],
             native_controllers: vec![],
             other_controllers: vec![],
+            also_known_as: vec![],
+            contexts: vec![],
+            authority_threshold: 1,
+            recovery_policy: None,
+            deactivated: false,
+            update_count: 0,
+            created_slot: 0,
+            created_at: 0,
+            updated_slot: 0,
+            updated_at: 0,
         }
     }
 }
@@ -62,9 +109,10 @@ impl Default for DidAccount {
 impl DidAccount {
     pub fn new(bump: u8, authority_key: &Pubkey) -> Self {
         Self {
-            version: 0,
+            version: DID_ACCOUNT_VERSION,
             bump,
             nonce: 0,
+            initial_authority: *authority_key,
             initial_verification_method: VerificationMethod::default(
                 VerificationMethodFlags::CAPABILITY_INVOCATION
                     | VerificationMethodFlags::OWNERSHIP_PROOF
@@ -75,18 +123,54 @@ impl DidAccount {
             services: vec![],
             native_controllers: vec![],
             other_controllers: vec![],
+            also_known_as: vec![],
+            contexts: vec![],
+            authority_threshold: 1,
+            recovery_policy: None,
+            deactivated: false,
+            update_count: 0,
+            created_slot: 0,
+            created_at: 0,
+            updated_slot: 0,
+            updated_at: 0,
         }
     }
 
     pub fn init(&mut self, bump: u8, authority_key: &Pubkey, flags: VerificationMethodFlags) {
-        self.version = 0;
+        self.version = DID_ACCOUNT_VERSION;
         self.bump = bump;
         self.nonce = 0;
+        self.initial_authority = *authority_key;
+        self.authority_threshold = 1;
+        self.recovery_policy = None;
+        self.deactivated = false;
+        self.update_count = 0;
 
         self.initial_verification_method =
             VerificationMethod::default(flags, authority_key.to_bytes().to_vec());
     }
 
+    /// Sets the creation (and last update) slot and unix timestamp to now.
+    pub fn record_creation(&mut self) -> Result<()> {
+        let clock = Clock::get()?;
+        self.created_slot = clock.slot;
+        self.created_at = clock.unix_timestamp;
+        self.updated_slot = clock.slot;
+        self.updated_at = clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    /// Increments the update count and sets the last update slot and unix timestamp to now.
+    pub fn record_update(&mut self) -> Result<()> {
+        let clock = Clock::get()?;
+        self.update_count += 1;
+        self.updated_slot = clock.slot;
+        self.updated_at = clock.unix_timestamp;
+
+        Ok(())
+    }
+
     /// Accessor for all verification methods (including the initial one)
     /// Enables to pass several filters that are ANDed together.
     pub fn verification_methods(
@@ -167,6 +251,18 @@ impl DidAccount {
             .is_empty()
     }
 
+    /// Returns true if the verification method `fragment` has a guarded flag (OWNERSHIP_PROOF or
+    /// PROTECTED). Such a verification method can only be changed by itself.
+    pub fn has_guarded_verification_method(&self, fragment: &String) -> bool {
+        self.verification_methods(None, None, None, Some(fragment))
+            .iter()
+            .any(|vm| {
+                VerificationMethodFlags::from_bits_truncate(vm.flags).intersects(
+                    VerificationMethodFlags::OWNERSHIP_PROOF | VerificationMethodFlags::PROTECTED,
+                )
+            })
+    }
+
     pub fn remove_verification_method(&mut self, fragment: &String) -> Result<()> {
         // default case
         if fragment == &self.initial_verification_method.fragment {
@@ -193,86 +289,467 @@ impl DidAccount {
             .next()
     }
 
+    /// Returns true if enough authority verification methods without an expiry exist
+    /// to meet the `authority_threshold`.
+    /// Verification methods with a `valid_until` or restricted permissions cannot prevent a lockout.
+    /// Like in [`DidAccount::check_authority_threshold`], only one detached (secp256k1 or P-256)
+    /// signature counts towards the threshold, all further signatures are Ed25519 signers.
     pub fn has_authority_verification_methods(&self) -> bool {
-        !self
+        let ed25519_types = VerificationMethodType::ed25519_types();
+        let (ed25519_authorities, detached_authorities): (Vec<_>, Vec<_>) = self
             .verification_methods(
                 Some(&VerificationMethodType::authority_types()),
                 Some(VerificationMethodFlags::CAPABILITY_INVOCATION),
                 None,
                 None,
             )
-            .is_empty()
+            .into_iter()
+            .filter(|vm| vm.valid_until.is_none() && vm.permissions().is_all())
+            .partition(|vm| {
+                matches!(
+                    VerificationMethodType::from_u8(vm.method_type),
+                    Some(method_type) if ed25519_types.contains(&method_type)
+                )
+            });
+        let authorities = ed25519_authorities.len() + detached_authorities.len().min(1);
+
+        authorities > 0 && authorities >= self.authority_threshold as usize
+    }
+
+    /// Checks that at least `authority_threshold` distinct authority verification methods signed.
+    /// `authority_fragment` is the verification method that authorized the instruction.
+    /// `additional_signers` are further Ed25519 signers, usually passed as remaining accounts.
+    /// Only signers with the `permission` for the instruction are counted.
+    pub fn check_authority_threshold(
+        &self,
+        authority_fragment: &str,
+        additional_signers: &[AccountInfo],
+        permission: InstructionPermissions,
+    ) -> Result<()> {
+        if self.authority_threshold <= 1 {
+            return Ok(());
+        }
+
+        let ed25519_types = VerificationMethodType::ed25519_types();
+        let signatures = additional_signers
+            .iter()
+            .filter(|signer| signer.is_signer)
+            .filter_map(|signer| {
+                self.find_authority_with_permission(
+                    &signer.key.to_bytes(),
+                    Some(&ed25519_types),
+                    None,
+                    permission,
+                )
+            })
+            .map(|vm| vm.fragment.as_str())
+            .chain(std::iter::once(authority_fragment))
+            .unique()
+            .count();
+
+        require!(
+            signatures >= self.authority_threshold as usize,
+            DidSolError::AuthorityThresholdNotMet
+        );
+
+        Ok(())
+    }
+
+    /// Authorizes an instruction that changes the DID and does the common bookkeeping: checks the
+    /// authority threshold, records the update and increments the nonce if a detached signature
+    /// (eth_signature or offline Ed25519 signature) was used.
+    /// Must be called before any other mutation. `message` is the payload of the `instruction`,
+    /// detached signatures sign its version 1 message (see [`signed_update_message`]) followed by
+    /// the nonce.
+    /// The authorizing verification method needs the `permission` for the instruction.
+    /// `remaining_accounts` can contain additional Ed25519 signers and the instructions sysvar.
+    /// An authority of a controlling DID can authorize the update as well, if the controller chain
+    /// is passed as remaining accounts (see [`DidAccount::find_controller_authority`]).
+    /// Returns the authorizing verification method.
+    #[allow(clippy::too_many_arguments)]
+    pub fn authorize_update(
+        &mut self,
+        sol_authority: &Pubkey,
+        instruction: &str,
+        message: &[u8],
+        typed_data: Option<Eip712Struct>,
+        eth_raw_signature: Option<&Secp256k1RawSignature>,
+        filter_fragment: Option<&String>,
+        permission: InstructionPermissions,
+        remaining_accounts: &[AccountInfo],
+    ) -> Result<UpdateAuthority> {
+        let mut detached_signature = eth_raw_signature.is_some();
+        let did_account =
+            derive_did_account_with_bump(&self.initial_authority.to_bytes(), self.bump)?;
+        let signed_message = signed_update_message(&crate::ID, &did_account, instruction, message);
+        let typed_data_hash = typed_data.map(|typed_data| {
+            typed_data.signing_hash(
+                &format!("{}{}", DID_SOL_PREFIX, self.initial_authority),
+                self.nonce,
+            )
+        });
+        let authority = self
+            .find_authority_constraint(
+                sol_authority,
+                &signed_message,
+                typed_data_hash.as_ref(),
+                eth_raw_signature,
+                filter_fragment,
+                permission,
+            )
+            .map(UpdateAuthority::from);
+        // controllers cannot act in place of a specific verification method
+        let authority = match (authority, filter_fragment) {
+            (Some(authority), _) => Some(authority),
+            (None, None) => self.find_controller_authority(sol_authority, remaining_accounts)?,
+            (None, Some(_)) => None,
+        };
+        let authority = match authority {
+            Some(authority) => authority,
+            None => {
+                let instructions_sysvar = remaining_accounts
+                    .iter()
+                    .find(|account| sysvar::instructions::check_id(account.key))
+                    .ok_or_else(|| error!(DidSolError::WrongAuthorityForDid))?;
+                detached_signature = true;
+                self.find_ed25519_offline_authority(
+                    &signed_message,
+                    instructions_sysvar,
+                    filter_fragment,
+                    permission,
+                )
+                .or_else(|| {
+                    self.find_webauthn_authority(
+                        &signed_message,
+                        instructions_sysvar,
+                        filter_fragment,
+                        permission,
+                    )
+                })
+                .map(UpdateAuthority::from)
+                .ok_or_else(|| error!(DidSolError::WrongAuthorityForDid))?
+            }
+        };
+        self.check_authority_threshold(&authority.fragment, remaining_accounts, permission)?;
+        self.record_update()?;
+        if detached_signature {
+            self.nonce += 1;
+        }
+
+        Ok(authority)
     }
 
+    /// Finds the authority `sol_authority` on a DID that controls this DID, directly or through a
+    /// chain of controlling DIDs. The chain is passed as the DID accounts in `remaining_accounts`,
+    /// in the order this -> remaining_accounts[0] -> ... -> remaining_accounts[n] -> authority,
+    /// where '->' represents the relationship "is controlled by".
+    /// The generative DID of `sol_authority` can end the chain, its (system owned) account must be
+    /// passed to show that the DID was not created, and its key rotated or deactivated since.
+    /// The authority threshold of the controlling DID applies, the authority can authorize all
+    /// instructions on this DID.
+    pub fn find_controller_authority(
+        &self,
+        sol_authority: &Pubkey,
+        remaining_accounts: &[AccountInfo],
+    ) -> Result<Option<UpdateAuthority>> {
+        let controller_chain: Vec<DidAccount> = remaining_accounts
+            .iter()
+            .filter_map(|account| DidAccount::try_from_account_info(account).ok())
+            .collect();
+        if !self.is_controlled_by(&controller_chain) {
+            return Ok(None);
+        }
+
+        let controller = controller_chain.last().unwrap_or(self);
+        if let Some(vm) = controller_chain.last().and_then(|controller| {
+            controller.find_authority(
+                &sol_authority.to_bytes(),
+                Some(&VerificationMethodType::ed25519_types()),
+                None,
+            )
+        }) {
+            controller.check_authority_threshold(
+                &vm.fragment,
+                remaining_accounts,
+                InstructionPermissions::all(),
+            )?;
+            return Ok(Some(UpdateAuthority::controller(
+                &controller.authority_key(),
+                &vm.fragment,
+            )));
+        }
+
+        // a generative DID, the only authority is the key itself. Once the DID account exists, it
+        // was parsed into the chain above, so the key must be one of its authorities
+        let (generative_did, _) = derive_did_account(&sol_authority.to_bytes());
+        let is_generative = remaining_accounts
+            .iter()
+            .any(|account| account.key == &generative_did && account.owner == &System::id());
+        if is_generative && controller.native_controllers.contains(sol_authority) {
+            return Ok(Some(UpdateAuthority::controller(
+                sol_authority,
+                VM_DEFAULT_FRAGMENT_NAME,
+            )));
+        }
+
+        Ok(None)
+    }
+
+    pub fn set_authority_threshold(&mut self, authority_threshold: u8) -> Result<()> {
+        require!(
+            authority_threshold > 0,
+            DidSolError::InvalidAuthorityThreshold
+        );
+        self.authority_threshold = authority_threshold;
+
+        Ok(())
+    }
+
+    /// Finds an Ed25519 authority that signed `message` (followed by the nonce) offline.
+    /// The signature must be verified by a native Ed25519 program instruction that precedes the
+    /// current instruction in the transaction, `instructions_sysvar` is the instructions sysvar.
+    pub fn find_ed25519_offline_authority(
+        &self,
+        message: &[u8],
+        instructions_sysvar: &AccountInfo,
+        filter_fragment: Option<&String>,
+        permission: InstructionPermissions,
+    ) -> Option<&VerificationMethod> {
+        let message_with_nonce = [message, self.nonce.to_le_bytes().as_ref()].concat();
+
+        preceding_instructions(instructions_sysvar, &ed25519_program::ID)
+            .iter()
+            .flat_map(|instruction| {
+                precompile_verified_signatures(&instruction.data, ED25519_PUBKEY_SIZE)
+            })
+            .filter(|signature| signature.message == message_with_nonce.as_slice())
+            .find_map(|signature| {
+                self.find_authority_with_permission(
+                    signature.public_key,
+                    Some(&VerificationMethodType::ed25519_types()),
+                    filter_fragment,
+                    permission,
+                )
+            })
+    }
+
+    /// Finds a passkey authority that approved `message` (followed by the nonce) with a WebAuthn
+    /// assertion. The WebAuthn challenge is sha256(message || nonce). The assertion signature must
+    /// be verified by a native secp256r1 program instruction that precedes the current instruction,
+    /// with the clientDataJSON of the assertion following the signed data in the instruction data.
+    /// The key data of the passkey holds the rpIdHash the assertion must be made for.
+    pub fn find_webauthn_authority(
+        &self,
+        message: &[u8],
+        instructions_sysvar: &AccountInfo,
+        filter_fragment: Option<&String>,
+        permission: InstructionPermissions,
+    ) -> Option<&VerificationMethod> {
+        let message_with_nonce = [message, self.nonce.to_le_bytes().as_ref()].concat();
+        let challenge = hash(&message_with_nonce);
+
+        preceding_instructions(instructions_sysvar, &SECP256R1_PROGRAM_ID)
+            .iter()
+            .flat_map(|instruction| {
+                precompile_verified_signatures(&instruction.data, SECP256R1_COMPRESSED_PUBKEY_SIZE)
+            })
+            .find_map(|signature| {
+                let key_data = [
+                    signature.public_key,
+                    webauthn_rp_id_hash(signature.message)?,
+                ]
+                .concat();
+                self.find_authority_with_permission(
+                    &key_data,
+                    Some(&[VerificationMethodType::EcdsaSecp256r1VerificationKey2019]),
+                    filter_fragment,
+                    permission,
+                )
+                .filter(|vm| {
+                    webauthn_verify_assertion(
+                        signature.message,
+                        signature.trailing_data,
+                        challenge.as_ref(),
+                        &vm.key_data[SECP256R1_COMPRESSED_PUBKEY_SIZE..],
+                    )
+                })
+            })
+    }
+
+    /// Finds the authority of an instruction that needs the `permission`.
     pub fn find_authority_constraint(
         &self,
         sol_authority: &Pubkey,
         eth_message: &[u8],
+        eth_typed_data_hash: Option<&[u8; 32]>,
         eth_raw_signature: Option<&Secp256k1RawSignature>,
         filter_fragment: Option<&String>,
+        permission: InstructionPermissions,
     ) -> Option<&VerificationMethod> {
         // find sol authority
-        let vm = self.find_authority(
+        let vm = self.find_authority_with_permission(
             &sol_authority.to_bytes(),
-            Some(&[VerificationMethodType::Ed25519VerificationKey2018]),
+            Some(&VerificationMethodType::ed25519_types()),
             filter_fragment,
+            permission,
         );
         if vm.is_some() {
             return vm;
         }
 
         if let Some(eth_raw_signature) = eth_raw_signature {
-            // recover key
-            let secp256k1_pubkey = eth_verify_message(
+            // recover key, the signature is either over a personal message or EIP-712 typed data
+            let vm = eth_verify_message(
                 eth_message,
                 self.nonce,
                 eth_raw_signature.signature,
                 eth_raw_signature.recovery_id,
             )
-            .ok()?;
-
-            let vm = self.find_authority(
-                &secp256k1_pubkey.to_bytes(),
-                Some(&[VerificationMethodType::EcdsaSecp256k1VerificationKey2019]),
-                filter_fragment,
-            );
+            .ok()
+            .and_then(|secp256k1_pubkey| {
+                self.find_secp256k1_authority(&secp256k1_pubkey, filter_fragment, permission)
+            });
             if vm.is_some() {
                 return vm;
             }
 
-            let address = convert_secp256k1pub_key_to_address(&secp256k1_pubkey);
-            let vm = self.find_authority(
-                &address,
-                Some(&[VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020]),
-                filter_fragment,
-            );
-            if vm.is_some() {
-                return vm;
+            if let Some(eth_typed_data_hash) = eth_typed_data_hash {
+                let vm = eth_verify_typed_data(
+                    eth_typed_data_hash,
+                    eth_raw_signature.signature,
+                    eth_raw_signature.recovery_id,
+                )
+                .ok()
+                .and_then(|secp256k1_pubkey| {
+                    self.find_secp256k1_authority(&secp256k1_pubkey, filter_fragment, permission)
+                });
+                if vm.is_some() {
+                    return vm;
+                }
             }
         }
 
         None
     }
 
+    /// Finds the EcdsaSecp256k1VerificationKey2019 or EcdsaSecp256k1RecoveryMethod2020 authority
+    /// of a recovered secp256k1 key.
+    fn find_secp256k1_authority(
+        &self,
+        secp256k1_pubkey: &Secp256k1Pubkey,
+        filter_fragment: Option<&String>,
+        permission: InstructionPermissions,
+    ) -> Option<&VerificationMethod> {
+        let vm = self.find_authority_with_permission(
+            &secp256k1_pubkey.to_bytes(),
+            Some(&[VerificationMethodType::EcdsaSecp256k1VerificationKey2019]),
+            filter_fragment,
+            permission,
+        );
+        if vm.is_some() {
+            return vm;
+        }
+
+        let address = convert_secp256k1pub_key_to_address(secp256k1_pubkey);
+        self.find_authority_with_permission(
+            &address,
+            Some(&[VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020]),
+            filter_fragment,
+            permission,
+        )
+    }
+
+    /// Finds an authority with unrestricted permissions.
     pub fn find_authority(
         &self,
         key: &[u8],
         filter_types: Option<&[VerificationMethodType]>,
         filter_fragment: Option<&String>,
     ) -> Option<&VerificationMethod> {
-        // msg!("Checking if key {:?} is an authority", key,);
-        self.verification_methods(
+        self.find_authority_with_permission(
+            key,
             filter_types,
-            Some(VerificationMethodFlags::CAPABILITY_INVOCATION),
-            Some(key),
             filter_fragment,
+            InstructionPermissions::all(),
         )
-        .into_iter()
-        .next()
     }
 
+    /// Finds an authority whose permissions contain `permission`.
+    pub fn find_authority_with_permission(
+        &self,
+        key: &[u8],
+        filter_types: Option<&[VerificationMethodType]>,
+        filter_fragment: Option<&String>,
+        permission: InstructionPermissions,
+    ) -> Option<&VerificationMethod> {
+        // msg!("Checking if key {:?} is an authority", key,);
+        if self.deactivated {
+            return None;
+        }
+
+        let filter = AuthorityFilter::new(key, filter_types, filter_fragment, permission);
+        std::iter::once(&self.initial_verification_method)
+            .chain(self.verification_methods.iter())
+            .find(|vm| filter.matches(&VerificationMethodRef::from(*vm)))
+    }
+
+    /// The key the DID identifier is derived from (did:sol:<authority_key>).
     pub fn authority_key(&self) -> Pubkey {
-        Pubkey::new(self.initial_verification_method.key_data.as_slice())
+        self.initial_authority
+    }
+
+    /// Replaces the key (and optionally the type) of the verification method `fragment`, keeping
+    /// its fragment and flags. Guarded flags (OWNERSHIP_PROOF and PROTECTED) are only kept if the
+    /// new key is an Ed25519 key among the `signers` of the transaction.
+    pub fn set_verification_method_key(
+        &mut self,
+        fragment: &String,
+        key_data: Vec<u8>,
+        method_type: Option<u8>,
+        signers: &[Pubkey],
+    ) -> Result<()> {
+        let vm = self
+            .find_verification_method(fragment)
+            .ok_or_else(|| error!(DidSolError::VmFragmentNotFound))?;
+
+        let mut replaced = vm.clone();
+        replaced.key_data = key_data;
+        replaced.method_type = method_type.unwrap_or(vm.method_type);
+        require!(replaced.has_valid_key_data(), DidSolError::VmInvalidKeyData);
+        let is_ed25519 = matches!(
+            VerificationMethodType::from_u8(replaced.method_type),
+            Some(method_type) if VerificationMethodType::ed25519_types().contains(&method_type)
+        );
+        let new_key_signed = is_ed25519
+            && signers
+                .iter()
+                .any(|signer| signer.as_ref() == replaced.key_data.as_slice());
+        if !new_key_signed {
+            let flags = VerificationMethodFlags::from_bits(replaced.flags)
+                .ok_or(DidSolError::ConversionError)?;
+            replaced.flags = (flags
+                - (VerificationMethodFlags::OWNERSHIP_PROOF | VerificationMethodFlags::PROTECTED))
+                .bits();
+        }
+        *vm = replaced;
+
+        Ok(())
+    }
+
+    /// Replaces the key of the initial verification method, keeping its fragment and flags.
+    /// The DID identifier and account address remain derived from `initial_authority`.
+    /// The new key is an Ed25519 key, so a re-typed initial verification method is reset to
+    /// `Ed25519VerificationKey2018`.
+    pub fn rotate_initial_authority(&mut self, new_authority: &Pubkey) {
+        let vm = &mut self.initial_verification_method;
+        vm.key_data = new_authority.to_bytes().to_vec();
+        let is_ed25519 = matches!(
+            VerificationMethodType::from_u8(vm.method_type),
+            Some(method_type) if VerificationMethodType::ed25519_types().contains(&method_type)
+        );
+        if !is_ed25519 {
+            vm.method_type = VerificationMethodType::Ed25519VerificationKey2018 as u8;
+        }
     }
 
     /// Returns true if `other` is a valid controller of this DID
@@ -309,27 +786,43 @@ impl DidAccount {
             allow_duplicates || unique_services.len() == original_size,
             DidSolError::ServiceFragmentAlreadyInUse
         );
+        for service in &unique_services {
+            service.validate()?;
+        }
 
         self.services = unique_services;
         Ok(())
     }
 
+    /// Replaces the types and / or the endpoint of the service `fragment`, keeping its position.
+    pub fn update_service(
+        &mut self,
+        fragment: &str,
+        service_types: Option<Vec<String>>,
+        service_endpoint: Option<ServiceEndpoint>,
+    ) -> Result<()> {
+        let service = self
+            .services
+            .iter_mut()
+            .find(|service| service.fragment == fragment)
+            .ok_or_else(|| error!(DidSolError::ServiceFragmentNotFound))?;
+
+        if let Some(service_types) = service_types {
+            service.service_types = service_types;
+        }
+        if let Some(service_endpoint) = service_endpoint {
+            service.service_endpoint = service_endpoint;
+        }
+
+        service.validate()
+    }
+
     pub fn set_verification_methods(
         &mut self,
         existing: Vec<VerificationMethod>,
         incoming: Vec<VerificationMethod>,
     ) -> Result<()> {
-        // check that incoming VMs do NOT set any Ownership or Protected flags.
-        incoming.iter().try_for_each(|vm| {
-            match VerificationMethodFlags::from_bits(vm.flags)
-                .ok_or(DidSolError::ConversionError)?
-                .intersects(
-                    VerificationMethodFlags::OWNERSHIP_PROOF | VerificationMethodFlags::PROTECTED,
-                ) {
-                true => Err(DidSolError::VmGuardedFlagOnAdd),
-                false => Ok(()),
-            }
-        })?;
+        DidAccount::check_incoming_verification_methods(&incoming)?;
 
         let methods = [existing, incoming].concat();
         let original_size = methods.len();
@@ -357,10 +850,112 @@ impl DidAccount {
         Ok(())
     }
 
+    pub fn add_verification_method(
+        &mut self,
+        verification_method: VerificationMethod,
+    ) -> Result<()> {
+        DidAccount::check_incoming_verification_methods(std::slice::from_ref(
+            &verification_method,
+        ))?;
+        require!(
+            !self.has_verification_method(&verification_method.fragment),
+            DidSolError::VmFragmentAlreadyInUse
+        );
+        self.verification_methods.push(verification_method);
+
+        Ok(())
+    }
+
+    /// Returns true if a verification method (including the initial one) uses `fragment`.
+    pub fn has_verification_method(&self, fragment: &str) -> bool {
+        // the existing verification methods are only compared, not copied
+        std::iter::once(&self.initial_verification_method)
+            .chain(self.verification_methods.iter())
+            .any(|vm| vm.fragment == fragment)
+    }
+
+    /// Checks that a recovery can add `verification_method`: a well formed CAPABILITY_INVOCATION
+    /// authority with unrestricted permissions, whose fragment is not in use.
+    pub fn check_recovery_verification_method(
+        &self,
+        verification_method: &VerificationMethod,
+    ) -> Result<()> {
+        DidAccount::check_incoming_verification_methods(std::slice::from_ref(verification_method))?;
+        require!(
+            VerificationMethodFlags::from_bits_truncate(verification_method.flags)
+                .contains(VerificationMethodFlags::CAPABILITY_INVOCATION)
+                && VerificationMethodType::is_authority_type(verification_method.method_type)
+                && verification_method.permissions().is_all(),
+            DidSolError::InvalidRecoveryVerificationMethod
+        );
+        require!(
+            !self.has_verification_method(&verification_method.fragment),
+            DidSolError::VmFragmentAlreadyInUse
+        );
+
+        Ok(())
+    }
+
+    /// Checks that incoming verification methods are well formed and do not set guarded flags.
+    fn check_incoming_verification_methods(incoming: &[VerificationMethod]) -> Result<()> {
+        // check that incoming VMs have a valid validity window
+        require!(
+            incoming.iter().all(|vm| vm.has_valid_window()),
+            DidSolError::VmInvalidValidityWindow
+        );
+
+        // check that incoming VMs have a known type and matching key data
+        require!(
+            incoming.iter().all(|vm| vm.has_valid_key_data()),
+            DidSolError::VmInvalidKeyData
+        );
+
+        // check that incoming VMs do NOT set any Ownership or Protected flags.
+        incoming.iter().try_for_each(|vm| {
+            match VerificationMethodFlags::from_bits(vm.flags)
+                .ok_or(DidSolError::ConversionError)?
+                .intersects(
+                    VerificationMethodFlags::OWNERSHIP_PROOF | VerificationMethodFlags::PROTECTED,
+                ) {
+                true => Err(DidSolError::VmGuardedFlagOnAdd),
+                false => Ok(()),
+            }
+        })?;
+
+        Ok(())
+    }
+
+    pub fn set_recovery_policy(&mut self, recovery_policy: Option<RecoveryPolicy>) -> Result<()> {
+        if let Some(recovery_policy) = &recovery_policy {
+            require!(
+                recovery_policy.is_valid(&self.authority_key()),
+                DidSolError::InvalidRecoveryPolicy
+            );
+        }
+        self.recovery_policy = recovery_policy;
+
+        Ok(())
+    }
+
+    /// Turns the DID into a tombstone: all verification methods, services, controllers and the
+    /// recovery policy are removed and the initial verification method loses all flags.
+    pub fn deactivate(&mut self) {
+        self.initial_verification_method.flags = VerificationMethodFlags::NONE.bits();
+        self.verification_methods = vec![];
+        self.services = vec![];
+        self.native_controllers = vec![];
+        self.other_controllers = vec![];
+        self.also_known_as = vec![];
+        self.contexts = vec![];
+        self.authority_threshold = 1;
+        self.recovery_policy = None;
+        self.deactivated = true;
+    }
+
     pub fn set_native_controllers(&mut self, native_controllers: Vec<Pubkey>) -> Result<()> {
         self.native_controllers = native_controllers.into_iter().unique().collect_vec();
 
-        let own_authority = Pubkey::new(&self.initial_verification_method.key_data);
+        let own_authority = self.authority_key();
 
         require!(
             !self.native_controllers.contains(&own_authority),
@@ -381,6 +976,22 @@ impl DidAccount {
         Ok(())
     }
 
+    pub fn add_also_known_as(&mut self, uri: String) -> Result<()> {
+        add_uri(&mut self.also_known_as, uri)
+    }
+
+    pub fn remove_also_known_as(&mut self, uri: &str) -> Result<()> {
+        remove_uri(&mut self.also_known_as, uri)
+    }
+
+    pub fn add_context(&mut self, uri: String) -> Result<()> {
+        add_uri(&mut self.contexts, uri)
+    }
+
+    pub fn remove_context(&mut self, uri: &str) -> Result<()> {
+        remove_uri(&mut self.contexts, uri)
+    }
+
     // Support generative and non-generative accounts
     pub fn try_from(
         did_account: &AccountInfo,
@@ -411,32 +1022,170 @@ impl DidAccount {
             return Ok(DidAccount::new(bump, initial_authority));
         }
         // Non-generative account
-        let did_account: Account<DidAccount> = Account::try_from(did_account)?;
-        Ok(did_account.into_inner())
+        DidAccount::try_from_account_info(did_account)
+    }
+
+    /// Deserializes a DidAccount of any supported version. Accounts with a previous layout are
+    /// upgraded in memory, so that integrations keep working until `upgrade_account` is called.
+    pub fn try_from_account_info(did_account: &AccountInfo) -> Result<DidAccount> {
+        require_keys_eq!(
+            *did_account.owner,
+            crate::ID,
+            ErrorCode::AccountOwnedByWrongProgram
+        );
+        DidAccount::try_from_account_data(&did_account.try_borrow_data()?)
+    }
+
+    /// Deserializes the account data of a DidAccount of any supported version (including the
+    /// discriminator).
+    pub fn try_from_account_data(data: &[u8]) -> Result<DidAccount> {
+        match data.get(8).copied() {
+            Some(0) => DidAccountV0::try_from_account_data(data)?.upgrade(),
+            Some(1) => DidAccountV1::try_from_account_data(data)?.upgrade(),
+            Some(2) => DidAccountV2::try_from_account_data(data)?.upgrade(),
+            Some(3) => DidAccountV3::try_from_account_data(data)?.upgrade(),
+            Some(DID_ACCOUNT_VERSION) => DidAccount::try_deserialize(&mut &data[..]),
+            _ => err!(DidSolError::UnsupportedAccountVersion),
+        }
     }
 
     pub fn size(&self) -> usize {
         1 // version
             + 1 // bump
             + 8 // nonce
-            + VerificationMethod::default_size() // initial_authority
+            + 32 // initial_authority
+            + self.initial_verification_method.size() // initial_verification_method
             + 4 + self.verification_methods.iter().fold(0, |accum, item| { accum + item.size() }) // verification_methods
             + 4 + self.services.iter().fold(0, |accum, item| { accum + item.size() }) // services
             + 4 + self.native_controllers.len() * 32 // native_controllers
-            + 4 + self.other_controllers.iter().fold(0, |accum, item| { accum + 4 + item.len() })
-        // other_controllers
+            + 4 + self.other_controllers.iter().fold(0, |accum, item| { accum + 4 + item.len() }) // other_controllers
+            + 4 + self.also_known_as.iter().fold(0, |accum, item| { accum + 4 + item.len() }) // also_known_as
+            + 4 + self.contexts.iter().fold(0, |accum, item| { accum + 4 + item.len() }) // contexts
+            + 1 // authority_threshold
+            + 1 + self.recovery_policy.as_ref().map_or(0, |policy| policy.size()) // recovery_policy
+            + 1 // deactivated
+            + 8 // update_count
+            + 8 // created_slot
+            + 8 // created_at
+            + 8 // updated_slot
+            + 8 // updated_at
     }
 
     pub fn initial_size() -> usize {
         1 // version
             + 1 // bump
             + 8 // nonce
-            + VerificationMethod::default_size() // initial_authority
+            + 32 // initial_authority
+            + VerificationMethod::default_size() // initial_verification_method
             + 4 // verification_methods
             + 4 // services
             + 4 // native_controllers
             + 4 // other_controllers
+            + 4 // also_known_as
+            + 4 // contexts
+            + 1 // authority_threshold
+            + 1 // recovery_policy
+            + 1 // deactivated
+            + 8 // update_count
+            + 8 // created_slot
+            + 8 // created_at
+            + 8 // updated_slot
+            + 8 // updated_at
+    }
+
+    /// The size of this DID after [`DidAccount::deactivate`]
+    pub fn tombstone_size(&self) -> usize {
+        DidAccount::initial_size() - VerificationMethod::default_size()
+            + self.initial_verification_method.size()
+    }
+}
+
+/// The conditions a verification method has to meet to be an authority. Shared by [`DidAccount`]
+/// and [`DidAccountView`](crate::state::DidAccountView), which read the same verification methods.
+pub(crate) struct AuthorityFilter<'a> {
+    key: &'a [u8],
+    filter_types: Option<&'a [VerificationMethodType]>,
+    filter_fragment: Option<&'a String>,
+    permission: InstructionPermissions,
+    now: Option<i64>,
+}
+
+impl<'a> AuthorityFilter<'a> {
+    pub fn new(
+        key: &'a [u8],
+        filter_types: Option<&'a [VerificationMethodType]>,
+        filter_fragment: Option<&'a String>,
+        permission: InstructionPermissions,
+    ) -> Self {
+        AuthorityFilter {
+            key,
+            filter_types,
+            filter_fragment,
+            permission,
+            // VMs with a validity window are ignored if the current time is not available.
+            now: Clock::get().map(|clock| clock.unix_timestamp).ok(),
+        }
     }
+
+    pub fn matches(&self, vm: &VerificationMethodRef) -> bool {
+        VerificationMethodFlags::from_bits_truncate(vm.flags)
+            .contains(VerificationMethodFlags::CAPABILITY_INVOCATION)
+            && vm.key_data == self.key
+            && match self.filter_fragment {
+                Some(filter_fragment) => vm.fragment == filter_fragment,
+                None => true,
+            }
+            // e.g. key agreement keys can never be an authority, even if flagged as such
+            && VerificationMethodType::is_authority_type(vm.method_type)
+            // all Ed25519 types are accepted in place of each other
+            && match self.filter_types {
+                Some(filter_types) => VerificationMethodType::from_u8(vm.method_type)
+                    .map(|vm_type| filter_types.iter().any(|t| vm_type.is_compatible_with(t)))
+                    .unwrap_or(false),
+                None => true,
+            }
+            && vm.permissions().contains(self.permission)
+            && vm.is_valid_at(self.now)
+    }
+}
+
+/// Returns true if a validity window (`valid_from`, `valid_until`) contains the unix timestamp.
+/// An open window is always valid, any other window is invalid without a timestamp.
+pub(crate) fn is_valid_window_at(
+    valid_from: Option<i64>,
+    valid_until: Option<i64>,
+    unix_timestamp: Option<i64>,
+) -> bool {
+    if valid_from.is_none() && valid_until.is_none() {
+        return true;
+    }
+
+    match unix_timestamp {
+        Some(unix_timestamp) => {
+            let started = valid_from.map(|from| unix_timestamp >= from);
+            let expired = valid_until.map(|until| unix_timestamp >= until);
+            started.unwrap_or(true) && !expired.unwrap_or(false)
+        }
+        None => false,
+    }
+}
+
+/// Appends `uri` to a list of URIs (`also_known_as` or `contexts`)
+fn add_uri(uris: &mut Vec<String>, uri: String) -> Result<()> {
+    require!(is_valid_uri(&uri), DidSolError::InvalidUri);
+    require!(!uris.contains(&uri), DidSolError::UriAlreadyInUse);
+    uris.push(uri);
+    Ok(())
+}
+
+/// Removes `uri` from a list of URIs, keeping the order of the others
+fn remove_uri(uris: &mut Vec<String>, uri: &str) -> Result<()> {
+    let index = uris
+        .iter()
+        .position(|item| item == uri)
+        .ok_or_else(|| error!(DidSolError::UriNotFound))?;
+    uris.remove(index);
+    Ok(())
 }
 
 #[derive(
@@ -450,21 +1199,85 @@ pub enum VerificationMethodType {
     EcdsaSecp256k1RecoveryMethod2020,
     /// Verification Method for a full 32 bytes Secp256k1 Verification Key
     EcdsaSecp256k1VerificationKey2019,
+    /// Verification Method for a 33 bytes compressed P-256 (secp256r1) Key of a WebAuthn passkey,
+    /// followed by the 32 bytes rpIdHash (sha256 of the relying party id) it is used with
+    EcdsaSecp256r1VerificationKey2019,
+    /// Key Agreement Method for a 32 bytes X25519 Key
+    /// https://w3c-ccg.github.io/ld-cryptosuite-registry/#x25519keyagreementkey2019
+    X25519KeyAgreementKey2019,
+    /// Key Agreement Method for a 32 bytes X25519 Key
+    /// https://w3id.org/security/suites/x25519-2020/v1
+    X25519KeyAgreementKey2020,
+    /// Verification Method for any public key, key_data holds the UTF-8 JSON of the public JWK
+    /// https://w3c-ccg.github.io/lds-jws2020/
+    JsonWebKey2020,
+    /// Ed25519 Verification Method for a 32 bytes Ed25519 Key
+    /// https://w3id.org/security/suites/ed25519-2020/v1
+    Ed25519VerificationKey2020,
+    /// Multikey Verification Method for a 32 bytes Ed25519 Key
+    /// https://www.w3.org/TR/controller-document/#multikey
+    Multikey,
+    /// Verification Method for a 96 bytes compressed BLS12-381 G2 Key, e.g. for BBS+ signatures
+    /// https://w3c-ccg.github.io/ldp-bbs2020/#bls12381g2key2020
+    Bls12381G2Key2020,
 }
 
 impl VerificationMethodType {
-    pub fn authority_types() -> [VerificationMethodType; 3] {
+    pub fn authority_types() -> [VerificationMethodType; 6] {
         [
             VerificationMethodType::Ed25519VerificationKey2018,
+            VerificationMethodType::Ed25519VerificationKey2020,
+            VerificationMethodType::Multikey,
             VerificationMethodType::EcdsaSecp256k1VerificationKey2019,
             VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020,
+            VerificationMethodType::EcdsaSecp256r1VerificationKey2019,
+        ]
+    }
+
+    /// Types that hold a raw Ed25519 public key. They only differ in their representation in the
+    /// DID Document.
+    pub fn ed25519_types() -> [VerificationMethodType; 3] {
+        [
+            VerificationMethodType::Ed25519VerificationKey2018,
+            VerificationMethodType::Ed25519VerificationKey2020,
+            VerificationMethodType::Multikey,
         ]
     }
 
-    // pub fn is_authority_type(vm_type: u8) -> bool {
-    //     let vm_type = VerificationMethodType::from_u8(vm_type).unwrap();
-    //     VerificationMethodType::authority_types().contains(&vm_type)
-    // }
+    /// Returns true if a verification method of this type can be used where `other` is expected,
+    /// i.e. both types hold the same kind of key.
+    pub fn is_compatible_with(&self, other: &VerificationMethodType) -> bool {
+        let ed25519_types = VerificationMethodType::ed25519_types();
+        self == other || (ed25519_types.contains(self) && ed25519_types.contains(other))
+    }
+
+    pub fn is_authority_type(vm_type: u8) -> bool {
+        matches!(
+            VerificationMethodType::from_u8(vm_type),
+            Some(vm_type) if VerificationMethodType::authority_types().contains(&vm_type)
+        )
+    }
+
+    pub fn is_valid_key_data(&self, key_data: &[u8]) -> bool {
+        match self {
+            VerificationMethodType::Ed25519VerificationKey2018
+            | VerificationMethodType::Ed25519VerificationKey2020
+            | VerificationMethodType::Multikey => key_data.len() == 32,
+            VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020 => key_data.len() == 20,
+            VerificationMethodType::EcdsaSecp256k1VerificationKey2019 => key_data.len() == 64,
+            VerificationMethodType::EcdsaSecp256r1VerificationKey2019 => {
+                key_data.len() == WEBAUTHN_KEY_DATA_SIZE
+            }
+            VerificationMethodType::X25519KeyAgreementKey2019
+            | VerificationMethodType::X25519KeyAgreementKey2020 => key_data.len() == 32,
+            VerificationMethodType::Bls12381G2Key2020 => key_data.len() == 96,
+            VerificationMethodType::JsonWebKey2020 => {
+                key_data.first() == Some(&b'{')
+                    && key_data.last() == Some(&b'}')
+                    && std::str::from_utf8(key_data).is_ok()
+            }
+        }
+    }
 }
 
 impl Default for VerificationMethodType {
@@ -485,6 +1298,13 @@ pub struct VerificationMethod {
     // Type: VerificationMethodType- Anchor does not yet provide mappings for enums
     /// Dynamically sized key matching the given VerificationType
     pub key_data: Vec<u8>,
+    /// Unix timestamp before which the verification method is not valid (optional)
+    pub valid_from: Option<i64>,
+    /// Unix timestamp from which on the verification method is no longer valid (optional)
+    pub valid_until: Option<i64>,
+    /// Instructions the verification method can authorize, see `InstructionPermissions`.
+    /// All instructions if None.
+    pub permissions: Option<u32>,
 }
 
 impl VerificationMethod {
@@ -493,6 +1313,9 @@ impl VerificationMethod {
             + 2 // flags
             + 1 // method
             + 4 + self.key_data.len()
+            + 1 + self.valid_from.map_or(0, |_| 8) // valid_from
+            + 1 + self.valid_until.map_or(0, |_| 8) // valid_until
+            + 1 + self.permissions.map_or(0, |_| 4) // permissions
     }
 
     pub fn default(flags: VerificationMethodFlags, key_data: Vec<u8>) -> VerificationMethod {
@@ -501,6 +1324,9 @@ impl VerificationMethod {
             flags: flags.bits(),
             method_type: VerificationMethodType::default().to_u8().unwrap(),
             key_data,
+            valid_from: None,
+            valid_until: None,
+            permissions: None,
         }
     }
 
@@ -509,6 +1335,38 @@ impl VerificationMethod {
             + 2 // flags
             + 1 // method
             + 4 + 32 // ed25519 pubkey
+            + 1 // valid_from
+            + 1 // valid_until
+            + 1 // permissions
+    }
+
+    /// Returns true if the verification method is valid at the given unix timestamp.
+    /// A VM without validity window is always valid, a VM with a validity window is
+    /// never valid if no timestamp is given.
+    pub fn is_valid_at(&self, unix_timestamp: Option<i64>) -> bool {
+        is_valid_window_at(self.valid_from, self.valid_until, unix_timestamp)
+    }
+
+    /// Returns true if `valid_from` is before `valid_until` (if both are set)
+    pub fn has_valid_window(&self) -> bool {
+        match (self.valid_from, self.valid_until) {
+            (Some(from), Some(until)) => from < until,
+            _ => true,
+        }
+    }
+
+    /// Instructions the verification method can authorize
+    pub fn permissions(&self) -> InstructionPermissions {
+        self.permissions
+            .map(InstructionPermissions::from_bits_truncate)
+            .unwrap_or_else(InstructionPermissions::all)
+    }
+
+    /// Returns true if the key data is valid for the verification method type.
+    pub fn has_valid_key_data(&self) -> bool {
+        VerificationMethodType::from_u8(self.method_type)
+            .map(|method_type| method_type.is_valid_key_data(&self.key_data))
+            .unwrap_or(false)
     }
 }
 
@@ -516,13 +1374,115 @@ impl VerificationMethod {
 #[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
 pub struct Service {
     pub fragment: String,
-    pub service_type: String,
-    pub service_endpoint: String,
+    /// The types of the service, at least one
+    pub service_types: Vec<String>,
+    pub service_endpoint: ServiceEndpoint,
+    /// Additional properties of the service
+    pub properties: Vec<ServiceProperty>,
 }
 
 impl Service {
     pub fn size(&self) -> usize {
-        4 + self.fragment.len() + 4 + self.service_type.len() + 4 + self.service_endpoint.len()
+        4 + self.fragment.len() // fragment
+            + 4 + self.service_types.iter().fold(0, |accum, item| accum + 4 + item.len()) // service_types
+            + self.service_endpoint.size() // service_endpoint
+            + 4 + self.properties.iter().fold(0, |accum, item| accum + item.size())
+        // properties
+    }
+
+    /// Checks that the service has a type, a well formed endpoint and unique properties that do
+    /// not shadow the `id`, `type` and `serviceEndpoint` of the service.
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            !self.service_types.is_empty()
+                && self
+                    .service_types
+                    .iter()
+                    .all(|service_type| !service_type.is_empty()),
+            DidSolError::InvalidService
+        );
+        require!(
+            self.service_endpoint.is_valid(),
+            DidSolError::InvalidService
+        );
+        require!(
+            self.properties.iter().all(|property| property.is_valid()
+                && !RESERVED_SERVICE_PROPERTIES.contains(&property.name.as_str()))
+                && self
+                    .properties
+                    .iter()
+                    .map(|property| &property.name)
+                    .all_unique(),
+            DidSolError::InvalidService
+        );
+
+        Ok(())
+    }
+}
+
+/// Service properties that are defined by the [`Service`] itself
+const RESERVED_SERVICE_PROPERTIES: [&str; 3] = ["id", "type", "serviceEndpoint"];
+
+/// The endpoint of a [`Service`]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum ServiceEndpoint {
+    /// A single URI
+    Uri(String),
+    /// A JSON encoded map or set of URIs and maps, e.g. `{"origins":["https://example.com"]}`
+    Json(String),
+}
+
+impl Default for ServiceEndpoint {
+    fn default() -> Self {
+        ServiceEndpoint::Uri(String::new())
+    }
+}
+
+impl ServiceEndpoint {
+    pub fn size(&self) -> usize {
+        match self {
+            ServiceEndpoint::Uri(uri) => 1 + 4 + uri.len(),
+            ServiceEndpoint::Json(json) => 1 + 4 + json.len(),
+        }
+    }
+
+    /// A URI must not be empty, JSON must be an object or an array. The JSON itself is not
+    /// validated on-chain.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            ServiceEndpoint::Uri(uri) => !uri.is_empty(),
+            ServiceEndpoint::Json(json) => {
+                let json = json.trim();
+                (json.starts_with('{') && json.ends_with('}'))
+                    || (json.starts_with('[') && json.ends_with(']'))
+            }
+        }
+    }
+}
+
+/// An additional property of a [`Service`], e.g. `description`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ServiceProperty {
+    pub name: String,
+    /// The JSON encoded value, e.g. `"a string"` or `{"key":"value"}`. It is not validated on-chain.
+    pub value: String,
+}
+
+impl ServiceProperty {
+    /// Creates a property with a string value
+    pub fn string(name: &str, value: &str) -> Self {
+        ServiceProperty {
+            name: name.to_string(),
+            value: json_string(value),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        4 + self.name.len() + 4 + self.value.len()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        !self.name.is_empty() && !self.value.trim().is_empty()
     }
 }
 
@@ -532,6 +1492,50 @@ pub struct Secp256k1RawSignature {
     pub recovery_id: u8,
 }
 
+/// The verification method that authorized an update of a DID
+pub struct UpdateAuthority {
+    /// The fragment of the verification method, or its DID URL if it is on a controlling DID
+    pub fragment: String,
+    /// The instructions the verification method can authorize
+    pub permissions: InstructionPermissions,
+}
+
+impl From<&VerificationMethod> for UpdateAuthority {
+    fn from(vm: &VerificationMethod) -> Self {
+        UpdateAuthority {
+            fragment: vm.fragment.clone(),
+            permissions: vm.permissions(),
+        }
+    }
+}
+
+impl UpdateAuthority {
+    /// An authority of a controlling DID, it can authorize all instructions
+    pub fn controller(did: &Pubkey, fragment: &str) -> Self {
+        UpdateAuthority {
+            fragment: format!("{}{}#{}", DID_SOL_PREFIX, did, fragment),
+            permissions: InstructionPermissions::all(),
+        }
+    }
+
+    /// Checks that the authority may manage (add, remove, change) `verification_methods`, i.e. it
+    /// has all of their permissions. This prevents a verification method with restricted
+    /// permissions from escalating its permissions.
+    pub fn check_can_manage<'a>(
+        &self,
+        verification_methods: impl IntoIterator<Item = &'a VerificationMethod>,
+    ) -> Result<()> {
+        require!(
+            verification_methods
+                .into_iter()
+                .all(|vm| self.permissions.contains(vm.permissions())),
+            DidSolError::VmPermissionsExceedAuthority
+        );
+
+        Ok(())
+    }
+}
+
 bitflags! {
     pub struct VerificationMethodFlags: u16 {
         const NONE = 0;
@@ -553,3 +1557,933 @@ bitflags! {
         const PROTECTED = 1 << 7;
     }
 }
+
+bitflags! {
+    /// Instructions a CAPABILITY_INVOCATION verification method can authorize
+    pub struct InstructionPermissions: u32 {
+        const UPDATE = 1 << 0;
+        const CLOSE = 1 << 1;
+        const DEACTIVATE = 1 << 2;
+        const RESIZE = 1 << 3;
+        const ADD_VERIFICATION_METHOD = 1 << 4;
+        const REMOVE_VERIFICATION_METHOD = 1 << 5;
+        const SET_VM_FLAGS = 1 << 6;
+        const SET_VM_TYPE = 1 << 7;
+        const ADD_SERVICE = 1 << 8;
+        const REMOVE_SERVICE = 1 << 9;
+        const SET_CONTROLLERS = 1 << 10;
+        const ROTATE_INITIAL_AUTHORITY = 1 << 11;
+        const SET_AUTHORITY_THRESHOLD = 1 << 12;
+        const SET_RECOVERY_POLICY = 1 << 13;
+        const CANCEL_RECOVERY = 1 << 14;
+        const SET_VM_KEY = 1 << 15;
+        const SET_ALSO_KNOWN_AS = 1 << 16;
+        const SET_CONTEXTS = 1 << 17;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{
+        create_instructions_sysvar_data, create_precompile_instruction_data,
+        create_test_account_info, eth_sign_hash,
+    };
+    use crate::utils::derive_did_account;
+    use solana_program::keccak;
+    use solana_program::sysvar::instructions;
+
+    fn create_test_did(authority: &Pubkey) -> DidAccount {
+        DidAccount::new(255, authority)
+    }
+
+    fn create_test_vm(
+        fragment: &str,
+        key: &Pubkey,
+        flags: VerificationMethodFlags,
+    ) -> VerificationMethod {
+        VerificationMethod {
+            fragment: fragment.to_string(),
+            flags: flags.bits(),
+            method_type: VerificationMethodType::Ed25519VerificationKey2018 as u8,
+            key_data: key.to_bytes().to_vec(),
+            valid_from: None,
+            valid_until: None,
+            permissions: None,
+        }
+    }
+
+    /// A DID that sets every field, with optional values present
+    fn create_populated_test_did(authority: &Pubkey) -> DidAccount {
+        DidAccount {
+            version: DID_ACCOUNT_VERSION,
+            bump: 254,
+            nonce: 3,
+            initial_authority: *authority,
+            initial_verification_method: VerificationMethod::default(
+                VerificationMethodFlags::CAPABILITY_INVOCATION
+                    | VerificationMethodFlags::OWNERSHIP_PROOF,
+                authority.to_bytes().to_vec(),
+            ),
+            verification_methods: vec![
+                VerificationMethod {
+                    fragment: "windowed".to_string(),
+                    flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                    method_type: VerificationMethodType::Ed25519VerificationKey2020 as u8,
+                    key_data: Pubkey::new_unique().to_bytes().to_vec(),
+                    valid_from: Some(1_000),
+                    valid_until: Some(2_000),
+                    permissions: Some(InstructionPermissions::ADD_SERVICE.bits()),
+                },
+                create_test_vm(
+                    "assertion",
+                    &Pubkey::new_unique(),
+                    VerificationMethodFlags::ASSERTION,
+                ),
+            ],
+            services: vec![
+                Service {
+                    fragment: "linked-domains".to_string(),
+                    service_types: vec!["LinkedDomains".to_string()],
+                    service_endpoint: ServiceEndpoint::Json(
+                        r#"{"origins":["https://example.com"]}"#.to_string(),
+                    ),
+                    properties: vec![ServiceProperty::string("description", "Domains")],
+                },
+                Service {
+                    fragment: "messaging".to_string(),
+                    service_types: vec!["DIDCommMessaging".to_string(), "Other".to_string()],
+                    service_endpoint: ServiceEndpoint::Uri(
+                        "https://example.com/didcomm".to_string(),
+                    ),
+                    properties: vec![],
+                },
+            ],
+            native_controllers: vec![Pubkey::new_unique()],
+            other_controllers: vec!["did:ethr:0x1234".to_string()],
+            also_known_as: vec!["https://example.com".to_string()],
+            contexts: vec!["https://w3id.org/security/suites/ed25519-2020/v1".to_string()],
+            authority_threshold: 2,
+            recovery_policy: Some(RecoveryPolicy {
+                guardians: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+                threshold: 1,
+                challenge_period: 86_400,
+            }),
+            deactivated: true,
+            update_count: 5,
+            created_slot: 10,
+            created_at: 1_600_000_000,
+            updated_slot: 20,
+            updated_at: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_guarded_default_vm_can_only_be_rotated_by_itself() {
+        let authority = Pubkey::new_unique();
+        let other_authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        did.add_verification_method(create_test_vm(
+            "other",
+            &other_authority,
+            VerificationMethodFlags::CAPABILITY_INVOCATION,
+        ))
+        .unwrap();
+        let default_fragment = did.initial_verification_method.fragment.clone();
+        assert!(did.has_guarded_verification_method(&default_fragment));
+        assert!(!did.has_guarded_verification_method(&"other".to_string()));
+
+        // rotate_initial_authority filters on the default VM if it is guarded
+        let find_rotation_authority = |did: &DidAccount, key: &Pubkey| {
+            did.find_authority_constraint(
+                key,
+                &[],
+                None,
+                None,
+                Some(&default_fragment),
+                InstructionPermissions::ROTATE_INITIAL_AUTHORITY,
+            )
+            .map(|vm| vm.fragment.clone())
+        };
+        assert_eq!(
+            find_rotation_authority(&did, &authority),
+            Some(default_fragment.clone())
+        );
+        assert_eq!(find_rotation_authority(&did, &other_authority), None);
+
+        // without guarded flags, any authority can rotate the default VM
+        did.initial_verification_method.flags =
+            VerificationMethodFlags::CAPABILITY_INVOCATION.bits();
+        assert!(!did.has_guarded_verification_method(&default_fragment));
+    }
+
+    #[test]
+    fn test_rotate_initial_authority_resets_the_key_type() {
+        let authority = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+
+        // Ed25519 types are kept
+        did.initial_verification_method.method_type = VerificationMethodType::Multikey as u8;
+        did.rotate_initial_authority(&new_authority);
+        assert_eq!(
+            did.initial_verification_method.method_type,
+            VerificationMethodType::Multikey as u8
+        );
+        assert!(did.initial_verification_method.has_valid_key_data());
+
+        // a re-typed default VM does not keep a type that does not match the new key
+        let secret_key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+        let eth_key = libsecp256k1::PublicKey::from_secret_key(&secret_key);
+        did.set_verification_method_key(
+            &did.initial_verification_method.fragment.clone(),
+            eth_key.serialize()[1..].to_vec(),
+            Some(VerificationMethodType::EcdsaSecp256k1VerificationKey2019 as u8),
+            &[],
+        )
+        .unwrap();
+        did.rotate_initial_authority(&authority);
+        assert_eq!(
+            did.initial_verification_method.method_type,
+            VerificationMethodType::Ed25519VerificationKey2018 as u8
+        );
+        assert_eq!(
+            did.initial_verification_method.key_data,
+            authority.to_bytes().to_vec()
+        );
+        assert!(did.initial_verification_method.has_valid_key_data());
+    }
+
+    #[test]
+    fn test_verification_method_validity_window() {
+        let mut vm = create_test_vm(
+            "windowed",
+            &Pubkey::new_unique(),
+            VerificationMethodFlags::CAPABILITY_INVOCATION,
+        );
+        // without a window, a VM is always valid
+        assert!(vm.is_valid_at(None));
+        assert!(vm.is_valid_at(Some(1_000)));
+
+        vm.valid_from = Some(1_000);
+        vm.valid_until = Some(2_000);
+        assert!(!vm.is_valid_at(Some(999)));
+        assert!(vm.is_valid_at(Some(1_000)));
+        assert!(vm.is_valid_at(Some(1_999)));
+        assert!(!vm.is_valid_at(Some(2_000)));
+        assert!(!vm.is_valid_at(None));
+
+        vm.valid_from = None;
+        assert!(vm.is_valid_at(Some(i64::MIN)));
+        assert!(!vm.is_valid_at(Some(2_000)));
+
+        vm.valid_from = Some(1_000);
+        vm.valid_until = None;
+        assert!(!vm.is_valid_at(Some(999)));
+        assert!(vm.is_valid_at(Some(i64::MAX)));
+
+        assert!(vm.has_valid_window());
+        vm.valid_until = Some(1_000);
+        assert!(!vm.has_valid_window());
+    }
+
+    #[test]
+    fn test_authority_threshold() {
+        let authority = Pubkey::new_unique();
+        let second_authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        did.add_verification_method(create_test_vm(
+            "second",
+            &second_authority,
+            VerificationMethodFlags::CAPABILITY_INVOCATION,
+        ))
+        .unwrap();
+        assert!(did.set_authority_threshold(0).is_err());
+        did.set_authority_threshold(2).unwrap();
+        assert!(did.has_authority_verification_methods());
+
+        let mut lamports = 0;
+        let mut data = [];
+        let second_signer = AccountInfo::new(
+            &second_authority,
+            true,
+            false,
+            &mut lamports,
+            &mut data,
+            &crate::ID,
+            false,
+            0,
+        );
+        let check = |signers: &[AccountInfo]| {
+            did.check_authority_threshold("default", signers, InstructionPermissions::UPDATE)
+        };
+
+        // threshold not met
+        assert_eq!(
+            check(&[]).unwrap_err(),
+            error!(DidSolError::AuthorityThresholdNotMet)
+        );
+        check(std::slice::from_ref(&second_signer)).unwrap();
+
+        // the same VM does not count twice
+        assert!(did
+            .check_authority_threshold(
+                "second",
+                std::slice::from_ref(&second_signer),
+                InstructionPermissions::UPDATE
+            )
+            .is_err());
+
+        // signers need to sign and be authorities on the DID
+        let mut not_signing = second_signer.clone();
+        not_signing.is_signer = false;
+        assert!(check(&[not_signing]).is_err());
+        let unknown_authority = Pubkey::new_unique();
+        let mut unknown_signer = second_signer;
+        unknown_signer.key = &unknown_authority;
+        assert!(check(&[unknown_signer]).is_err());
+    }
+
+    #[test]
+    fn test_authority_threshold_counts_one_detached_signature() {
+        let authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        for fragment in ["eth-1", "eth-2"] {
+            did.add_verification_method(VerificationMethod {
+                method_type: VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020 as u8,
+                key_data: vec![1; 20],
+                ..create_test_vm(
+                    fragment,
+                    &Pubkey::new_unique(),
+                    VerificationMethodFlags::CAPABILITY_INVOCATION,
+                )
+            })
+            .unwrap();
+        }
+
+        // the default VM and one ethSignature can meet a threshold of two
+        did.set_authority_threshold(2).unwrap();
+        assert!(did.has_authority_verification_methods());
+        did.set_authority_threshold(3).unwrap();
+        assert!(!did.has_authority_verification_methods());
+
+        // two eth authorities cannot sign the same update
+        did.set_authority_threshold(2).unwrap();
+        did.initial_verification_method.flags = VerificationMethodFlags::NONE.bits();
+        assert!(!did.has_authority_verification_methods());
+        did.set_authority_threshold(1).unwrap();
+        assert!(did.has_authority_verification_methods());
+    }
+
+    #[test]
+    fn test_check_recovery_verification_method() {
+        let did = create_test_did(&Pubkey::new_unique());
+        let vm = create_test_vm(
+            "recovered",
+            &Pubkey::new_unique(),
+            VerificationMethodFlags::CAPABILITY_INVOCATION,
+        );
+        did.check_recovery_verification_method(&vm).unwrap();
+
+        // the same checks as add_verification_method
+        assert_eq!(
+            did.check_recovery_verification_method(&VerificationMethod {
+                fragment: "default".to_string(),
+                ..vm.clone()
+            })
+            .unwrap_err(),
+            error!(DidSolError::VmFragmentAlreadyInUse)
+        );
+        assert_eq!(
+            did.check_recovery_verification_method(&VerificationMethod {
+                flags: (VerificationMethodFlags::CAPABILITY_INVOCATION
+                    | VerificationMethodFlags::PROTECTED)
+                    .bits(),
+                ..vm.clone()
+            })
+            .unwrap_err(),
+            error!(DidSolError::VmGuardedFlagOnAdd)
+        );
+        assert_eq!(
+            did.check_recovery_verification_method(&VerificationMethod {
+                valid_from: Some(2_000),
+                valid_until: Some(1_000),
+                ..vm.clone()
+            })
+            .unwrap_err(),
+            error!(DidSolError::VmInvalidValidityWindow)
+        );
+        assert_eq!(
+            did.check_recovery_verification_method(&VerificationMethod {
+                key_data: vec![1; 20],
+                ..vm.clone()
+            })
+            .unwrap_err(),
+            error!(DidSolError::VmInvalidKeyData)
+        );
+
+        // the recovered verification method must be an unrestricted authority
+        for invalid in [
+            VerificationMethod {
+                flags: VerificationMethodFlags::AUTHENTICATION.bits(),
+                ..vm.clone()
+            },
+            VerificationMethod {
+                permissions: Some(InstructionPermissions::ADD_SERVICE.bits()),
+                ..vm.clone()
+            },
+            VerificationMethod {
+                method_type: VerificationMethodType::X25519KeyAgreementKey2019 as u8,
+                ..vm
+            },
+        ] {
+            assert_eq!(
+                did.check_recovery_verification_method(&invalid)
+                    .unwrap_err(),
+                error!(DidSolError::InvalidRecoveryVerificationMethod)
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_controller_authority() {
+        let controller_authority = Pubkey::new_unique();
+        let controlled_authority = Pubkey::new_unique();
+        let other_authority = Pubkey::new_unique();
+
+        let mut controller_did_account = create_test_did(&controller_authority);
+        let mut controlled_did_account = create_test_did(&controlled_authority);
+        controller_did_account
+            .set_native_controllers(vec![other_authority])
+            .unwrap();
+        controlled_did_account
+            .set_native_controllers(vec![controller_authority])
+            .unwrap();
+
+        let controller_did_account_address = derive_did_account(&controller_authority.to_bytes());
+        let mut controller_data: Vec<u8> = Vec::with_capacity(1024);
+        controller_did_account
+            .try_serialize(&mut controller_data)
+            .unwrap();
+        let mut controller_lamports = 1;
+        let controller_account_info = create_test_account_info(
+            &controller_did_account_address.0,
+            &crate::ID,
+            &mut controller_lamports,
+            &mut controller_data,
+        );
+        let controller_chain = [controller_account_info];
+
+        // an authority of the controlling DID
+        let authority = controlled_did_account
+            .find_controller_authority(&controller_authority, &controller_chain)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            authority.fragment,
+            format!("did:sol:{}#default", controller_authority)
+        );
+        assert!(authority.permissions.is_all());
+
+        // the generative DID of the controller of the controller, through the chain and its
+        // system owned account
+        let other_did_account_address = derive_did_account(&other_authority.to_bytes());
+        let mut other_data: Vec<u8> = vec![];
+        let mut other_lamports = 0;
+        let other_account_info = create_test_account_info(
+            &other_did_account_address.0,
+            &solana_program::system_program::ID,
+            &mut other_lamports,
+            &mut other_data,
+        );
+        let generative_chain = [controller_chain[0].clone(), other_account_info];
+        assert!(controlled_did_account
+            .find_controller_authority(&other_authority, &generative_chain)
+            .unwrap()
+            .is_some());
+        assert!(controlled_did_account
+            .find_controller_authority(&other_authority, &controller_chain)
+            .unwrap()
+            .is_none());
+        assert!(controlled_did_account
+            .find_controller_authority(&other_authority, &[])
+            .unwrap()
+            .is_none());
+
+        // keys of the controlled DID are no controller authorities
+        assert!(controlled_did_account
+            .find_controller_authority(&controlled_authority, &controller_chain)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_controller_authority_generative() {
+        let controller_authority = Pubkey::new_unique();
+        let controlled_authority = Pubkey::new_unique();
+        let mut controlled_did_account = create_test_did(&controlled_authority);
+        controlled_did_account
+            .set_native_controllers(vec![controller_authority])
+            .unwrap();
+
+        // the generative DID of the controller, its account does not exist
+        let controller_did_account_address = derive_did_account(&controller_authority.to_bytes());
+        let mut generative_data: Vec<u8> = vec![];
+        let mut generative_lamports = 0;
+        let generative_account_info = create_test_account_info(
+            &controller_did_account_address.0,
+            &solana_program::system_program::ID,
+            &mut generative_lamports,
+            &mut generative_data,
+        );
+        let authority = controlled_did_account
+            .find_controller_authority(&controller_authority, &[generative_account_info])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            authority.fragment,
+            format!("did:sol:{}#default", controller_authority)
+        );
+        assert!(controlled_did_account
+            .find_controller_authority(&controller_authority, &[])
+            .unwrap()
+            .is_none());
+
+        // the controller rotated its initial authority away from the key
+        let mut rotated_did_account = create_test_did(&controller_authority);
+        rotated_did_account.rotate_initial_authority(&Pubkey::new_unique());
+        let mut rotated_data: Vec<u8> = Vec::with_capacity(1024);
+        rotated_did_account
+            .try_serialize(&mut rotated_data)
+            .unwrap();
+        let mut rotated_lamports = 1;
+        let rotated_account_info = create_test_account_info(
+            &controller_did_account_address.0,
+            &crate::ID,
+            &mut rotated_lamports,
+            &mut rotated_data,
+        );
+        assert!(controlled_did_account
+            .find_controller_authority(&controller_authority, &[rotated_account_info])
+            .unwrap()
+            .is_none());
+
+        // the controller deactivated its DID
+        let mut deactivated_did_account = create_test_did(&controller_authority);
+        deactivated_did_account.deactivate();
+        let mut deactivated_data: Vec<u8> = Vec::with_capacity(1024);
+        deactivated_did_account
+            .try_serialize(&mut deactivated_data)
+            .unwrap();
+        let mut deactivated_lamports = 1;
+        let deactivated_account_info = create_test_account_info(
+            &controller_did_account_address.0,
+            &crate::ID,
+            &mut deactivated_lamports,
+            &mut deactivated_data,
+        );
+        assert!(controlled_did_account
+            .find_controller_authority(&controller_authority, &[deactivated_account_info])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_webauthn_authority() {
+        let test_authority = Pubkey::new_unique();
+        let passkey = [2; 33];
+        let rp_id_hash = hash(b"example.com");
+        let mut test_did_account = create_test_did(&test_authority);
+        test_did_account
+            .verification_methods
+            .push(VerificationMethod {
+                fragment: "passkey".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: VerificationMethodType::EcdsaSecp256r1VerificationKey2019 as u8,
+                key_data: [passkey.as_ref(), rp_id_hash.as_ref()].concat(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
+            });
+        assert!(test_did_account.verification_methods[0].has_valid_key_data());
+
+        let message = b"message";
+        let challenge = hash(&[message.as_ref(), 0u64.to_le_bytes().as_ref()].concat());
+        let create_sysvar_data = |rp_id: &[u8], user_present: u8, client_data_type: &str| {
+            let client_data_json = format!(
+                r#"{{"type":"{}","challenge":"{}","origin":"https://example.com"}}"#,
+                client_data_type,
+                base64::encode_config(challenge, base64::URL_SAFE_NO_PAD)
+            );
+            let mut signed_data = hash(rp_id).as_ref().to_vec();
+            signed_data.extend_from_slice(&[user_present, 0, 0, 0, 0]);
+            signed_data.extend_from_slice(hash(client_data_json.as_bytes()).as_ref());
+            let secp256r1_data = create_precompile_instruction_data(
+                &passkey,
+                &signed_data,
+                client_data_json.as_bytes(),
+            );
+            create_instructions_sysvar_data(&SECP256R1_PROGRAM_ID, &secp256r1_data)
+        };
+        let find_authority = |sysvar_data: &mut Vec<u8>, message: &[u8]| {
+            let mut lamports = 1;
+            let instructions_sysvar = create_test_account_info(
+                &instructions::ID,
+                &sysvar::ID,
+                &mut lamports,
+                sysvar_data,
+            );
+            test_did_account
+                .find_webauthn_authority(
+                    message,
+                    &instructions_sysvar,
+                    None,
+                    InstructionPermissions::UPDATE,
+                )
+                .map(|vm| vm.fragment.clone())
+        };
+
+        let mut sysvar_data = create_sysvar_data(b"example.com", 1, "webauthn.get");
+        assert_eq!(
+            find_authority(&mut sysvar_data, message),
+            Some("passkey".to_string())
+        );
+        // the challenge is bound to the message
+        assert_eq!(find_authority(&mut sysvar_data, b"other message"), None);
+
+        // the user must be present
+        let mut sysvar_data = create_sysvar_data(b"example.com", 0, "webauthn.get");
+        assert_eq!(find_authority(&mut sysvar_data, message), None);
+
+        // the assertion must be made for the relying party of the passkey
+        let mut sysvar_data = create_sysvar_data(b"attacker.com", 1, "webauthn.get");
+        assert_eq!(find_authority(&mut sysvar_data, message), None);
+
+        // registrations are no assertions
+        let mut sysvar_data = create_sysvar_data(b"example.com", 1, "webauthn.create");
+        assert_eq!(find_authority(&mut sysvar_data, message), None);
+    }
+
+    #[test]
+    fn test_bls_verification_method() {
+        let authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        let bls_key = [3; 96];
+        let create_bls_vm = |fragment: &str, key_data: &[u8]| VerificationMethod {
+            fragment: fragment.to_string(),
+            flags: (VerificationMethodFlags::ASSERTION
+                | VerificationMethodFlags::CAPABILITY_INVOCATION)
+                .bits(),
+            method_type: VerificationMethodType::Bls12381G2Key2020 as u8,
+            key_data: key_data.to_vec(),
+            valid_from: None,
+            valid_until: None,
+            permissions: None,
+        };
+
+        // only 96 bytes compressed G2 keys
+        let bls_type = VerificationMethodType::Bls12381G2Key2020;
+        assert!(bls_type.is_valid_key_data(&bls_key));
+        assert!(!bls_type.is_valid_key_data(&[3; 48]));
+        assert!(!bls_type.is_valid_key_data(&[3; 32]));
+        assert_eq!(
+            did.add_verification_method(create_bls_vm("bls", &[3; 48]))
+                .unwrap_err(),
+            error!(DidSolError::VmInvalidKeyData)
+        );
+        did.add_verification_method(create_bls_vm("bls", &bls_key))
+            .unwrap();
+
+        // BLS keys can not sign Solana transactions, so they are never an authority
+        assert!(!VerificationMethodType::is_authority_type(bls_type as u8));
+        assert!(did.find_authority(&bls_key, None, None).is_none());
+
+        // and can not be re-typed to a key of another kind
+        for other_type in VerificationMethodType::authority_types() {
+            assert!(!bls_type.is_compatible_with(&other_type));
+            assert!(!other_type.is_compatible_with(&bls_type));
+        }
+    }
+
+    #[test]
+    fn test_size_matches_serialization() {
+        // compact and the automatic reallocation size the account with `size`
+        let authority = Pubkey::new_unique();
+        let did = create_test_did(&authority);
+        assert_eq!(did.size(), DidAccount::initial_size());
+        assert_eq!(did.size(), did.try_to_vec().unwrap().len());
+
+        let did = create_populated_test_did(&authority);
+        assert_eq!(did.size(), did.try_to_vec().unwrap().len());
+    }
+
+    #[test]
+    fn test_authorize_update_rejects_replayed_signatures() {
+        let authority = Pubkey::new_unique();
+        let (did_account, bump) = derive_did_account(&authority.to_bytes());
+        // the transaction signer is not an authority of the DID
+        let relayer = Pubkey::new_unique();
+
+        let secret_key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+        let public_key = libsecp256k1::PublicKey::from_secret_key(&secret_key);
+        let address = convert_secp256k1pub_key_to_address(&Secp256k1Pubkey::new(
+            &public_key.serialize()[1..],
+        ));
+        let eth_vm = VerificationMethod {
+            fragment: "eth-address".to_string(),
+            flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+            method_type: VerificationMethodType::EcdsaSecp256k1RecoveryMethod2020 as u8,
+            key_data: address.to_vec(),
+            valid_from: None,
+            valid_until: None,
+            permissions: None,
+        };
+        let mut did = DidAccount::new(bump, &authority);
+        did.add_verification_method(eth_vm.clone()).unwrap();
+        // another DID with the same key and nonce
+        let other_authority = Pubkey::new_unique();
+        let (_, other_bump) = derive_did_account(&other_authority.to_bytes());
+        let mut other_did = DidAccount::new(other_bump, &other_authority);
+        other_did.add_verification_method(eth_vm).unwrap();
+
+        let eth_sign = |message: &[u8]| {
+            let message_with_nonce = [message, 0u64.to_le_bytes().as_ref()].concat();
+            let personal_message_hash = keccak::hashv(&[
+                b"\x19Ethereum Signed Message:\n",
+                message_with_nonce.len().to_string().as_bytes(),
+                &message_with_nonce,
+            ])
+            .to_bytes();
+            eth_sign_hash(&secret_key, &personal_message_hash)
+        };
+        let authorize_update =
+            |did: &mut DidAccount, instruction: &str, signature: &Secp256k1RawSignature| {
+                did.authorize_update(
+                    &relayer,
+                    instruction,
+                    &1000u32.to_le_bytes(),
+                    None,
+                    Some(signature),
+                    None,
+                    InstructionPermissions::all(),
+                    &[],
+                )
+                .err()
+                .unwrap()
+            };
+
+        // the version 1 message is bound to the DID and the instruction
+        let signature = eth_sign(&signed_update_message(
+            &crate::ID,
+            &did_account,
+            "resize",
+            &1000u32.to_le_bytes(),
+        ));
+        assert!(did
+            .find_authority_constraint(
+                &relayer,
+                &signed_update_message(&crate::ID, &did_account, "resize", &1000u32.to_le_bytes()),
+                None,
+                Some(&signature),
+                None,
+                InstructionPermissions::RESIZE,
+            )
+            .is_some());
+        assert_eq!(
+            authorize_update(&mut other_did, "resize", &signature),
+            error!(DidSolError::WrongAuthorityForDid)
+        );
+        assert_eq!(
+            authorize_update(&mut did, "set_authority_threshold", &signature),
+            error!(DidSolError::WrongAuthorityForDid)
+        );
+
+        // legacy signatures of the payload and nonce only can no longer be replayed
+        let legacy_signature = eth_sign(&1000u32.to_le_bytes());
+        assert_eq!(
+            authorize_update(&mut did, "resize", &legacy_signature),
+            error!(DidSolError::WrongAuthorityForDid)
+        );
+        assert_eq!(
+            authorize_update(&mut other_did, "resize", &legacy_signature),
+            error!(DidSolError::WrongAuthorityForDid)
+        );
+        assert_eq!(did.nonce, 0);
+    }
+
+    #[test]
+    fn test_update_service() {
+        let authority = Pubkey::new_unique();
+        let mut did = create_populated_test_did(&authority);
+        let original_services = did.services.clone();
+
+        assert_eq!(
+            did.update_service("missing", Some(vec!["LinkedDomains".to_string()]), None)
+                .unwrap_err(),
+            error!(DidSolError::ServiceFragmentNotFound)
+        );
+        assert_eq!(
+            did.services.try_to_vec().unwrap(),
+            original_services.try_to_vec().unwrap()
+        );
+
+        // the types only, keeping the endpoint, properties and position
+        did.update_service("linked-domains", Some(vec!["Other".to_string()]), None)
+            .unwrap();
+        assert_eq!(did.services[0].fragment, "linked-domains");
+        assert_eq!(did.services[0].service_types, vec!["Other".to_string()]);
+        assert_eq!(
+            did.services[0].service_endpoint,
+            original_services[0].service_endpoint
+        );
+        assert_eq!(did.services[0].properties, original_services[0].properties);
+        assert_eq!(
+            did.services[1].try_to_vec().unwrap(),
+            original_services[1].try_to_vec().unwrap()
+        );
+
+        // the endpoint only
+        let endpoint = ServiceEndpoint::Uri("https://example.org/didcomm".to_string());
+        did.update_service("messaging", None, Some(endpoint.clone()))
+            .unwrap();
+        assert_eq!(did.services[1].service_endpoint, endpoint);
+        assert_eq!(
+            did.services[1].service_types,
+            original_services[1].service_types
+        );
+
+        // the updated service must still be valid
+        assert_eq!(
+            did.update_service("messaging", Some(vec![]), None)
+                .unwrap_err(),
+            error!(DidSolError::InvalidService)
+        );
+    }
+
+    #[test]
+    fn test_set_verification_method_key() {
+        let test_authority = Pubkey::new_unique();
+        let guarded_flags = VerificationMethodFlags::CAPABILITY_INVOCATION
+            | VerificationMethodFlags::OWNERSHIP_PROOF
+            | VerificationMethodFlags::PROTECTED;
+        let default_fragment = VM_DEFAULT_FRAGMENT_NAME.to_string();
+
+        // guarded flags are kept if the new key co-signs
+        let new_key = Pubkey::new_unique();
+        let mut test_did_account = create_test_did(&test_authority);
+        test_did_account.initial_verification_method.flags = guarded_flags.bits();
+        test_did_account
+            .set_verification_method_key(
+                &default_fragment,
+                new_key.to_bytes().to_vec(),
+                None,
+                &[test_authority, new_key],
+            )
+            .unwrap();
+        assert_eq!(
+            test_did_account.initial_verification_method.flags,
+            guarded_flags.bits()
+        );
+        assert!(test_did_account
+            .find_authority(&new_key.to_bytes(), None, None)
+            .is_some());
+        assert!(test_did_account
+            .find_authority(&test_authority.to_bytes(), None, None)
+            .is_none());
+
+        // guarded flags are cleared otherwise, the fragment and other flags are kept
+        let mut test_did_account = create_test_did(&test_authority);
+        test_did_account.initial_verification_method.flags = guarded_flags.bits();
+        test_did_account
+            .set_verification_method_key(
+                &default_fragment,
+                new_key.to_bytes().to_vec(),
+                Some(VerificationMethodType::Ed25519VerificationKey2020 as u8),
+                &[test_authority],
+            )
+            .unwrap();
+        let vm = &test_did_account.initial_verification_method;
+        assert_eq!(vm.fragment, default_fragment);
+        assert_eq!(
+            vm.flags,
+            VerificationMethodFlags::CAPABILITY_INVOCATION.bits()
+        );
+        assert_eq!(
+            vm.method_type,
+            VerificationMethodType::Ed25519VerificationKey2020 as u8
+        );
+        assert_eq!(
+            test_did_account.size(),
+            create_test_did(&test_authority).size()
+        );
+
+        // the key data must match the type
+        assert_eq!(
+            test_did_account
+                .set_verification_method_key(
+                    &default_fragment,
+                    vec![1; 20],
+                    None,
+                    &[test_authority]
+                )
+                .unwrap_err(),
+            error!(DidSolError::VmInvalidKeyData)
+        );
+        assert_eq!(
+            test_did_account
+                .set_verification_method_key(
+                    &"unknown".to_string(),
+                    new_key.to_bytes().to_vec(),
+                    None,
+                    &[test_authority],
+                )
+                .unwrap_err(),
+            error!(DidSolError::VmFragmentNotFound)
+        );
+    }
+
+    #[test]
+    fn test_also_known_as_and_contexts() {
+        let mut test_did_account = create_test_did(&Pubkey::new_unique());
+        let initial_size = test_did_account.size();
+
+        test_did_account
+            .add_also_known_as("did:web:example.com".to_string())
+            .unwrap();
+        test_did_account
+            .add_context("https://w3id.org/security/suites/ed25519-2020/v1".to_string())
+            .unwrap();
+        assert_eq!(test_did_account.size(), initial_size + 4 + 19 + 4 + 48);
+
+        // duplicates and relative or malformed URIs are rejected
+        assert_eq!(
+            test_did_account
+                .add_also_known_as("did:web:example.com".to_string())
+                .unwrap_err(),
+            error!(DidSolError::UriAlreadyInUse)
+        );
+        for uri in [
+            "example.com",
+            "1http://example.com",
+            "https:",
+            "https://exa mple.com",
+        ] {
+            assert_eq!(
+                test_did_account.add_context(uri.to_string()).unwrap_err(),
+                error!(DidSolError::InvalidUri)
+            );
+        }
+
+        test_did_account
+            .remove_also_known_as("did:web:example.com")
+            .unwrap();
+        assert_eq!(
+            test_did_account
+                .remove_also_known_as("did:web:example.com")
+                .unwrap_err(),
+            error!(DidSolError::UriNotFound)
+        );
+        assert!(test_did_account.also_known_as.is_empty());
+        assert_eq!(test_did_account.contexts.len(), 1);
+    }
+}
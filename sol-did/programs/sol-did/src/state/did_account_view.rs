@@ -0,0 +1,556 @@
+//! Indexed access to the account data of a [`DidAccount`] without deserializing it.
+//!
+//! A [`DidAccountView`] walks the Borsh encoded account once when it is created and records the
+//! offsets of all verification methods and services (its offset table). Verification methods are
+//! then read in place and services are deserialized one by one. The view is read-only, updates go
+//! through [`DidAccount`].
+//!
+//! The view works on the current account layout ([`DID_ACCOUNT_VERSION`]), accounts with a
+//! previous layout need to be read with [`DidAccount::try_from_account_data`].
+use crate::constants::DID_ACCOUNT_VERSION;
+use crate::errors::DidSolError;
+use crate::state::did_account::{is_valid_window_at, AuthorityFilter};
+use crate::state::{
+    DidAccount, InstructionPermissions, Service, VerificationMethod, VerificationMethodType,
+};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use std::cell::Ref;
+use std::ops::Deref;
+
+const VERSION_OFFSET: usize = 8;
+const BUMP_OFFSET: usize = 9;
+const NONCE_OFFSET: usize = 10;
+const INITIAL_AUTHORITY_OFFSET: usize = 18;
+const INITIAL_VERIFICATION_METHOD_OFFSET: usize = 50;
+
+/// A verification method borrowed from the account data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerificationMethodRef<'a> {
+    pub fragment: &'a str,
+    pub flags: u16,
+    pub method_type: u8,
+    pub key_data: &'a [u8],
+    pub valid_from: Option<i64>,
+    pub valid_until: Option<i64>,
+    pub permissions: Option<u32>,
+}
+
+impl<'a> VerificationMethodRef<'a> {
+    fn read(cursor: &mut Cursor<'a>) -> Result<Self> {
+        Ok(VerificationMethodRef {
+            fragment: cursor.string()?,
+            flags: cursor.u16()?,
+            method_type: cursor.u8()?,
+            key_data: cursor.bytes()?,
+            valid_from: cursor.option(Cursor::i64)?,
+            valid_until: cursor.option(Cursor::i64)?,
+            permissions: cursor.option(Cursor::u32)?,
+        })
+    }
+
+    /// See [`VerificationMethod::is_valid_at`]
+    pub fn is_valid_at(&self, unix_timestamp: Option<i64>) -> bool {
+        is_valid_window_at(self.valid_from, self.valid_until, unix_timestamp)
+    }
+
+    /// See [`VerificationMethod::permissions`]
+    pub fn permissions(&self) -> InstructionPermissions {
+        self.permissions
+            .map(InstructionPermissions::from_bits_truncate)
+            .unwrap_or_else(InstructionPermissions::all)
+    }
+
+    pub fn to_verification_method(&self) -> VerificationMethod {
+        VerificationMethod {
+            fragment: self.fragment.to_string(),
+            flags: self.flags,
+            method_type: self.method_type,
+            key_data: self.key_data.to_vec(),
+            valid_from: self.valid_from,
+            valid_until: self.valid_until,
+            permissions: self.permissions,
+        }
+    }
+}
+
+impl<'a> From<&'a VerificationMethod> for VerificationMethodRef<'a> {
+    fn from(vm: &'a VerificationMethod) -> Self {
+        VerificationMethodRef {
+            fragment: &vm.fragment,
+            flags: vm.flags,
+            method_type: vm.method_type,
+            key_data: &vm.key_data,
+            valid_from: vm.valid_from,
+            valid_until: vm.valid_until,
+            permissions: vm.permissions,
+        }
+    }
+}
+
+/// Indexed view of the account data (including the discriminator) of a [`DidAccount`]
+pub struct DidAccountView<D> {
+    data: D,
+    /// Offsets of the verification methods, starting with the initial verification method
+    verification_methods: Vec<usize>,
+    /// Offsets of the services
+    services: Vec<usize>,
+    /// Offset of `deactivated`, the fixed size fields of the account follow it
+    deactivated: usize,
+}
+
+impl<'a> DidAccountView<Ref<'a, [u8]>> {
+    /// Creates a view of a DidAccount owned by this program.
+    pub fn try_from_account_info(did_account: &'a AccountInfo) -> Result<Self> {
+        require_keys_eq!(
+            *did_account.owner,
+            crate::ID,
+            ErrorCode::AccountOwnedByWrongProgram
+        );
+        let data = Ref::map(did_account.try_borrow_data()?, |data| &**data);
+        DidAccountView::try_from_account_data(data)
+    }
+}
+
+impl<D: Deref<Target = [u8]>> DidAccountView<D> {
+    /// Checks the discriminator and version of the account data and builds the offset table.
+    pub fn try_from_account_data(data: D) -> Result<Self> {
+        require!(
+            data.len() > VERSION_OFFSET && data[..8] == DidAccount::discriminator(),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        require!(
+            data[VERSION_OFFSET] == DID_ACCOUNT_VERSION,
+            DidSolError::UnsupportedAccountVersion
+        );
+
+        let mut cursor = Cursor::at(&data, INITIAL_VERIFICATION_METHOD_OFFSET);
+        let mut verification_methods = vec![cursor.offset];
+        VerificationMethodRef::read(&mut cursor)?;
+        cursor.vec(|cursor| {
+            verification_methods.push(cursor.offset);
+            VerificationMethodRef::read(cursor).map(|_| ())
+        })?;
+        let mut services = vec![];
+        cursor.vec(|cursor| {
+            services.push(cursor.offset);
+            skip_service(cursor)
+        })?;
+        cursor.vec(|cursor| cursor.take(32).map(|_| ()))?; // native_controllers
+        cursor.vec(|cursor| cursor.string().map(|_| ()))?; // other_controllers
+        cursor.vec(|cursor| cursor.string().map(|_| ()))?; // also_known_as
+        cursor.vec(|cursor| cursor.string().map(|_| ()))?; // contexts
+        cursor.u8()?; // authority_threshold
+        cursor.option(|cursor| {
+            // recovery_policy
+            cursor.vec(|cursor| cursor.take(32).map(|_| ()))?;
+            cursor.take(1 + 8).map(|_| ())
+        })?;
+        let deactivated = cursor.offset;
+        cursor.take(1 + 5 * 8)?;
+
+        Ok(DidAccountView {
+            data,
+            verification_methods,
+            services,
+            deactivated,
+        })
+    }
+
+    pub fn version(&self) -> u8 {
+        self.data[VERSION_OFFSET]
+    }
+
+    pub fn bump(&self) -> u8 {
+        self.data[BUMP_OFFSET]
+    }
+
+    pub fn nonce(&self) -> u64 {
+        Cursor::at(&self.data, NONCE_OFFSET).u64().unwrap()
+    }
+
+    pub fn initial_authority(&self) -> Pubkey {
+        Pubkey::new_from_array(
+            self.data[INITIAL_AUTHORITY_OFFSET..INITIAL_AUTHORITY_OFFSET + 32]
+                .try_into()
+                .unwrap(),
+        )
+    }
+
+    pub fn deactivated(&self) -> bool {
+        self.data[self.deactivated] != 0
+    }
+
+    /// Number of verification methods, including the initial verification method
+    pub fn verification_methods_len(&self) -> usize {
+        self.verification_methods.len()
+    }
+
+    /// The verification method at `index`, the initial verification method is at index 0.
+    pub fn verification_method(&self, index: usize) -> Result<VerificationMethodRef<'_>> {
+        let offset = *self
+            .verification_methods
+            .get(index)
+            .ok_or_else(|| error!(DidSolError::VmFragmentNotFound))?;
+        VerificationMethodRef::read(&mut Cursor::at(&self.data, offset))
+    }
+
+    /// Finds the index of the verification method `fragment`.
+    pub fn find_verification_method(&self, fragment: &str) -> Result<Option<usize>> {
+        for index in 0..self.verification_methods.len() {
+            if self.verification_method(index)?.fragment == fragment {
+                return Ok(Some(index));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn services_len(&self) -> usize {
+        self.services.len()
+    }
+
+    /// Deserializes the service at `index` only.
+    pub fn service(&self, index: usize) -> Result<Service> {
+        let offset = *self
+            .services
+            .get(index)
+            .ok_or_else(|| error!(DidSolError::ServiceFragmentNotFound))?;
+        Service::deserialize(&mut &self.data[offset..])
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))
+    }
+
+    /// See [`DidAccount::find_authority`]
+    pub fn find_authority(
+        &self,
+        key: &[u8],
+        filter_types: Option<&[VerificationMethodType]>,
+        filter_fragment: Option<&String>,
+    ) -> Result<Option<VerificationMethodRef<'_>>> {
+        self.find_authority_with_permission(
+            key,
+            filter_types,
+            filter_fragment,
+            InstructionPermissions::all(),
+        )
+    }
+
+    /// See [`DidAccount::find_authority_with_permission`]
+    pub fn find_authority_with_permission(
+        &self,
+        key: &[u8],
+        filter_types: Option<&[VerificationMethodType]>,
+        filter_fragment: Option<&String>,
+        permission: InstructionPermissions,
+    ) -> Result<Option<VerificationMethodRef<'_>>> {
+        if self.deactivated() {
+            return Ok(None);
+        }
+
+        let filter = AuthorityFilter::new(key, filter_types, filter_fragment, permission);
+        for index in 0..self.verification_methods.len() {
+            let vm = self.verification_method(index)?;
+            if filter.matches(&vm) {
+                return Ok(Some(vm));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+fn skip_service(cursor: &mut Cursor) -> Result<()> {
+    cursor.string()?; // fragment
+    cursor.vec(|cursor| cursor.string().map(|_| ()))?; // service_types
+    cursor.u8()?; // service_endpoint kind
+    cursor.string()?; // service_endpoint
+    cursor.vec(|cursor| {
+        // properties
+        cursor.string()?;
+        cursor.string().map(|_| ())
+    })?;
+
+    Ok(())
+}
+
+/// Reads Borsh encoded values from a position of the account data
+struct Cursor<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn at(data: &'a [u8], offset: usize) -> Self {
+        Cursor { data, offset }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| error!(ErrorCode::AccountDidNotDeserialize))?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<&'a str> {
+        std::str::from_utf8(self.bytes()?).map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))
+    }
+
+    fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T>) -> Result<Option<T>> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            _ => err!(ErrorCode::AccountDidNotDeserialize),
+        }
+    }
+
+    /// Calls `read` for every item of a vector.
+    fn vec(&mut self, mut read: impl FnMut(&mut Self) -> Result<()>) -> Result<()> {
+        let len = self.u32()?;
+        (0..len).try_for_each(|_| read(self))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::{RecoveryPolicy, ServiceEndpoint, ServiceProperty, VerificationMethodFlags};
+
+    fn create_test_did(authority: &Pubkey) -> DidAccount {
+        let mut did = DidAccount::new(254, authority);
+        did.nonce = 42;
+        did.add_verification_method(VerificationMethod {
+            fragment: "key-1".to_string(),
+            flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+            method_type: VerificationMethodType::Ed25519VerificationKey2018 as u8,
+            key_data: Pubkey::new_unique().to_bytes().to_vec(),
+            valid_from: Some(10),
+            valid_until: None,
+            permissions: Some(InstructionPermissions::ADD_SERVICE.bits()),
+        })
+        .unwrap();
+        did.services = vec![
+            Service {
+                fragment: "profile".to_string(),
+                service_types: vec!["Profile".to_string()],
+                service_endpoint: ServiceEndpoint::Uri("https://example.com".to_string()),
+                properties: vec![ServiceProperty::string("description", "my profile")],
+            },
+            Service {
+                fragment: "messaging".to_string(),
+                service_types: vec!["DIDCommMessaging".to_string()],
+                service_endpoint: ServiceEndpoint::Json(
+                    r#"{"uri":"https://example.com"}"#.to_string(),
+                ),
+                properties: vec![],
+            },
+        ];
+        did.native_controllers = vec![Pubkey::new_unique()];
+        did.other_controllers = vec!["did:ethr:0x1234".to_string()];
+        did.also_known_as = vec!["did:web:example.com".to_string()];
+        did.recovery_policy = Some(RecoveryPolicy {
+            guardians: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            threshold: 2,
+            challenge_period: 3_600,
+        });
+        did.update_count = 5;
+        did.created_at = 1_000;
+        did.updated_at = 2_000;
+        did
+    }
+
+    fn serialize(did: &DidAccount) -> Vec<u8> {
+        let mut data = vec![];
+        did.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_view_reads_entries_in_place() {
+        let authority = Pubkey::new_unique();
+        let did = create_test_did(&authority);
+        let data = serialize(&did);
+
+        let view = DidAccountView::try_from_account_data(data.as_slice()).unwrap();
+        assert_eq!(view.version(), DID_ACCOUNT_VERSION);
+        assert_eq!(view.bump(), 254);
+        assert_eq!(view.nonce(), 42);
+        assert_eq!(view.initial_authority(), authority);
+        assert!(!view.deactivated());
+
+        assert_eq!(view.verification_methods_len(), 2);
+        let vm = view.verification_method(1).unwrap();
+        assert_eq!(vm.fragment, "key-1");
+        assert_eq!(vm.key_data, did.verification_methods[0].key_data.as_slice());
+        assert_eq!(vm.valid_from, Some(10));
+        assert_eq!(
+            vm.permissions,
+            Some(InstructionPermissions::ADD_SERVICE.bits())
+        );
+        assert_eq!(view.find_verification_method("key-1").unwrap(), Some(1));
+        assert_eq!(view.find_verification_method("unknown").unwrap(), None);
+
+        assert_eq!(view.services_len(), 2);
+        let service = view.service(1).unwrap();
+        assert_eq!(service.fragment, "messaging");
+        assert_eq!(service.service_endpoint, did.services[1].service_endpoint);
+        assert!(view.service(2).is_err());
+    }
+
+    #[test]
+    fn test_view_finds_authority() {
+        let authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        let data = serialize(&did);
+        let view = DidAccountView::try_from_account_data(data.as_slice()).unwrap();
+
+        let found = view
+            .find_authority(&authority.to_bytes(), None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.fragment, did.initial_verification_method.fragment);
+        // restricted permissions and validity windows are respected
+        let key = did.verification_methods[0].key_data.clone();
+        assert!(view.find_authority(&key, None, None).unwrap().is_none());
+        assert!(view
+            .find_authority(&Pubkey::new_unique().to_bytes(), None, None)
+            .unwrap()
+            .is_none());
+
+        did.deactivate();
+        let data = serialize(&did);
+        let view = DidAccountView::try_from_account_data(data.as_slice()).unwrap();
+        assert!(view.deactivated());
+        assert!(view
+            .find_authority(&authority.to_bytes(), None, None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_view_round_trip() {
+        let authority = Pubkey::new_unique();
+        let mut did = create_test_did(&authority);
+        did.contexts = vec!["https://w3id.org/security/suites/ed25519-2020/v1".to_string()];
+        did.authority_threshold = 2;
+        did.created_slot = 10;
+        did.updated_slot = 20;
+        did.deactivate();
+        let data = serialize(&did);
+        let view = DidAccountView::try_from_account_data(data.as_slice()).unwrap();
+
+        // no `..`: a new DidAccount field has to be covered here
+        let DidAccount {
+            version,
+            bump,
+            nonce,
+            initial_authority,
+            initial_verification_method,
+            verification_methods,
+            services,
+            native_controllers,
+            other_controllers,
+            also_known_as,
+            contexts,
+            authority_threshold,
+            recovery_policy,
+            deactivated,
+            update_count,
+            created_slot,
+            created_at,
+            updated_slot,
+            updated_at,
+        } = did;
+
+        assert_eq!(view.version(), version);
+        assert_eq!(view.bump(), bump);
+        assert_eq!(view.nonce(), nonce);
+        assert_eq!(view.initial_authority(), initial_authority);
+        assert_eq!(view.deactivated(), deactivated);
+
+        let all_verification_methods =
+            [vec![initial_verification_method], verification_methods].concat();
+        assert_eq!(
+            view.verification_methods_len(),
+            all_verification_methods.len()
+        );
+        for (index, vm) in all_verification_methods.iter().enumerate() {
+            let vm_ref = view.verification_method(index).unwrap();
+            assert_eq!(
+                vm_ref.to_verification_method().try_to_vec().unwrap(),
+                vm.try_to_vec().unwrap()
+            );
+        }
+
+        assert_eq!(view.services_len(), services.len());
+        for (index, service) in services.iter().enumerate() {
+            assert_eq!(
+                view.service(index).unwrap().try_to_vec().unwrap(),
+                service.try_to_vec().unwrap()
+            );
+        }
+
+        // the fields between the services and `deactivated` are skipped by the offset table
+        let mut skipped = vec![];
+        native_controllers.serialize(&mut skipped).unwrap();
+        other_controllers.serialize(&mut skipped).unwrap();
+        also_known_as.serialize(&mut skipped).unwrap();
+        contexts.serialize(&mut skipped).unwrap();
+        authority_threshold.serialize(&mut skipped).unwrap();
+        recovery_policy.serialize(&mut skipped).unwrap();
+        assert_eq!(
+            &data[view.deactivated - skipped.len()..view.deactivated],
+            skipped.as_slice()
+        );
+
+        let mut cursor = Cursor::at(&data, view.deactivated + 1);
+        assert_eq!(cursor.u64().unwrap(), update_count);
+        assert_eq!(cursor.u64().unwrap(), created_slot);
+        assert_eq!(cursor.i64().unwrap(), created_at);
+        assert_eq!(cursor.u64().unwrap(), updated_slot);
+        assert_eq!(cursor.i64().unwrap(), updated_at);
+        assert_eq!(cursor.offset, data.len());
+    }
+
+    #[test]
+    fn test_view_rejects_invalid_data() {
+        let did = create_test_did(&Pubkey::new_unique());
+        let data = serialize(&did);
+
+        assert!(DidAccountView::try_from_account_data(&data[..data.len() - 1]).is_err());
+        assert!(DidAccountView::try_from_account_data(&data[1..]).is_err());
+
+        let mut previous_version = data.clone();
+        previous_version[VERSION_OFFSET] = 1;
+        assert!(DidAccountView::try_from_account_data(previous_version.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+use crate::constants::RECOVERY_EXECUTION_PERIOD;
+use crate::errors::DidSolError;
+use crate::integrations::{derive_did_account, is_authority};
+use crate::state::{DidAccount, VerificationMethod, VerificationMethodType};
+use anchor_lang::prelude::*;
+use itertools::Itertools;
+
+/// Social recovery configuration of a [`DidAccount`](crate::state::DidAccount)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct RecoveryPolicy {
+    /// Guardian DIDs - did:sol:<guardian>. A plain key is a guardian through its generative DID.
+    pub guardians: Vec<Pubkey>,
+    /// Number of guardians that need to approve a recovery
+    pub threshold: u8,
+    /// Seconds that need to pass between starting and executing a recovery
+    pub challenge_period: i64,
+}
+
+impl RecoveryPolicy {
+    pub fn size(&self) -> usize {
+        4 + self.guardians.len() * 32 // guardians
+            + 1 // threshold
+            + 8 // challenge_period
+    }
+
+    /// Returns true if the guardians are unique, do not include `own_identifier`
+    /// and the threshold can be met.
+    pub fn is_valid(&self, own_identifier: &Pubkey) -> bool {
+        let threshold = self.threshold as usize;
+        self.guardians.iter().all_unique()
+            && !self.guardians.contains(own_identifier)
+            && threshold > 0
+            && threshold <= self.guardians.len()
+            && self.challenge_period >= 0
+    }
+
+    /// Checks that `guardian` is part of this policy, that `guardian_did` is the DID account
+    /// of `guardian` and that `guardian_authority` is an authority on it.
+    pub fn check_guardian(
+        &self,
+        guardian: &Pubkey,
+        guardian_did: &AccountInfo,
+        guardian_authority: &Pubkey,
+    ) -> Result<()> {
+        require!(
+            self.guardians.contains(guardian)
+                && derive_did_account(&guardian.to_bytes()).0 == *guardian_did.key,
+            DidSolError::InvalidGuardian
+        );
+        require!(
+            is_authority(
+                guardian_did,
+                None,
+                &[],
+                &guardian_authority.to_bytes(),
+                Some(&VerificationMethodType::ed25519_types()),
+                None,
+            )?,
+            DidSolError::InvalidGuardian
+        );
+
+        Ok(())
+    }
+
+    /// Checks that `guardians` jointly meet the threshold of this policy. `guardian_accounts` are
+    /// the DID account and a signing authority of every guardian, in the order of `guardians`.
+    pub fn check_guardians(
+        &self,
+        guardians: &[Pubkey],
+        guardian_accounts: &[AccountInfo],
+    ) -> Result<()> {
+        require!(
+            guardians.iter().all_unique() && guardian_accounts.len() == 2 * guardians.len(),
+            DidSolError::InvalidGuardian
+        );
+        guardians
+            .iter()
+            .zip(guardian_accounts.chunks(2))
+            .try_for_each(|(guardian, accounts)| {
+                require!(accounts[1].is_signer, ErrorCode::AccountNotSigner);
+                self.check_guardian(guardian, &accounts[0], accounts[1].key)
+            })?;
+        require!(
+            self.count_approvals(guardians) >= self.threshold as usize,
+            DidSolError::RecoveryThresholdNotMet
+        );
+
+        Ok(())
+    }
+
+    /// Number of approvals of guardians that are (still) part of this policy
+    pub fn count_approvals(&self, approvals: &[Pubkey]) -> usize {
+        approvals
+            .iter()
+            .filter(|guardian| self.guardians.contains(guardian))
+            .count()
+    }
+}
+
+/// A pending recovery of a [`DidAccount`](crate::state::DidAccount)
+#[account]
+pub struct DidRecovery {
+    /// The DID account that is recovered
+    pub did_account: Pubkey,
+    /// Bump
+    pub bump: u8,
+    /// The account that paid the rent and receives it when the recovery is closed
+    pub payer: Pubkey,
+    /// The CAPABILITY_INVOCATION verification method added to the DID by the recovery
+    pub verification_method: VerificationMethod,
+    /// Guardians that approved the recovery
+    pub approvals: Vec<Pubkey>,
+    /// Unix timestamp the recovery was started at
+    pub started_at: i64,
+    /// Unix timestamp from which on the recovery can no longer be executed
+    pub expires_at: i64,
+}
+
+impl DidRecovery {
+    pub fn size(verification_method: &VerificationMethod, guardians: usize) -> usize {
+        32 // did_account
+            + 1 // bump
+            + 32 // payer
+            + verification_method.size() // verification_method
+            + 4 + guardians * 32 // approvals
+            + 8 // started_at
+            + 8 // expires_at
+    }
+
+    /// Starts the recovery at `now`. It can be executed within [`RECOVERY_EXECUTION_PERIOD`]
+    /// seconds after the challenge period of the `policy`.
+    pub fn start(&mut self, policy: &RecoveryPolicy, now: i64) {
+        self.started_at = now;
+        self.expires_at = now
+            .saturating_add(policy.challenge_period)
+            .saturating_add(RECOVERY_EXECUTION_PERIOD);
+    }
+
+    /// Checks that the recovery can be executed at `now` under the current `policy` of the DID.
+    pub fn check_executable(&self, policy: &RecoveryPolicy, now: i64) -> Result<()> {
+        // the policy might have changed since the recovery was started
+        require!(
+            policy.count_approvals(&self.approvals) >= policy.threshold as usize,
+            DidSolError::RecoveryThresholdNotMet
+        );
+        require!(
+            now >= self.started_at.saturating_add(policy.challenge_period),
+            DidSolError::RecoveryChallengePeriodActive
+        );
+        require!(now < self.expires_at, DidSolError::RecoveryExpired);
+
+        Ok(())
+    }
+
+    /// Returns true if the recovery can never be executed on `did`: it expired, the DID has no
+    /// recovery policy anymore or the fragment of the verification method is in use.
+    pub fn is_stale(&self, did: &DidAccount, now: i64) -> bool {
+        now >= self.expires_at
+            || did.recovery_policy.is_none()
+            || did.has_verification_method(&self.verification_method.fragment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::{InstructionPermissions, VerificationMethodFlags};
+
+    struct TestGuardian {
+        key: Pubkey,
+        did: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        authority_lamports: u64,
+        authority_data: Vec<u8>,
+    }
+
+    impl TestGuardian {
+        fn new() -> Self {
+            let key = Pubkey::new_unique();
+            TestGuardian {
+                key,
+                did: derive_did_account(&key.to_bytes()).0,
+                lamports: 0,
+                data: vec![],
+                authority_lamports: 0,
+                authority_data: vec![],
+            }
+        }
+
+        /// The (generative) guardian DID account and the signing guardian key
+        fn accounts(&mut self) -> [AccountInfo<'_>; 2] {
+            [
+                AccountInfo::new(
+                    &self.did,
+                    false,
+                    false,
+                    &mut self.lamports,
+                    &mut self.data,
+                    &solana_program::system_program::ID,
+                    false,
+                    0,
+                ),
+                AccountInfo::new(
+                    &self.key,
+                    true,
+                    false,
+                    &mut self.authority_lamports,
+                    &mut self.authority_data,
+                    &solana_program::system_program::ID,
+                    false,
+                    0,
+                ),
+            ]
+        }
+    }
+
+    fn create_test_policy(guardians: &[&TestGuardian], threshold: u8) -> RecoveryPolicy {
+        RecoveryPolicy {
+            guardians: guardians.iter().map(|guardian| guardian.key).collect(),
+            threshold,
+            challenge_period: 100,
+        }
+    }
+
+    fn create_test_recovery(policy: &RecoveryPolicy) -> DidRecovery {
+        let mut recovery = DidRecovery {
+            did_account: Pubkey::new_unique(),
+            bump: 255,
+            payer: Pubkey::new_unique(),
+            verification_method: VerificationMethod {
+                fragment: "recovered".to_string(),
+                flags: VerificationMethodFlags::CAPABILITY_INVOCATION.bits(),
+                method_type: VerificationMethodType::Ed25519VerificationKey2018 as u8,
+                key_data: Pubkey::new_unique().to_bytes().to_vec(),
+                valid_from: None,
+                valid_until: None,
+                permissions: None,
+            },
+            approvals: policy.guardians[..policy.threshold as usize].to_vec(),
+            started_at: 0,
+            expires_at: 0,
+        };
+        recovery.start(policy, 1_000);
+        recovery
+    }
+
+    #[test]
+    fn test_guardians_start_a_recovery_jointly() {
+        let mut alice = TestGuardian::new();
+        let mut bob = TestGuardian::new();
+        let mut eve = TestGuardian::new();
+        let policy = create_test_policy(&[&alice, &bob, &TestGuardian::new()], 2);
+        let (alice_key, bob_key, eve_key) = (alice.key, bob.key, eve.key);
+        let alice_accounts = alice.accounts();
+        let bob_accounts = bob.accounts();
+        let eve_accounts = eve.accounts();
+
+        // a single guardian cannot start a recovery
+        assert_eq!(
+            policy
+                .check_guardians(&[alice_key], &alice_accounts)
+                .unwrap_err(),
+            error!(DidSolError::RecoveryThresholdNotMet)
+        );
+        let both_accounts = [alice_accounts.clone(), bob_accounts.clone()].concat();
+        policy
+            .check_guardians(&[alice_key, bob_key], &both_accounts)
+            .unwrap();
+
+        // guardians count once and need their DID and authority
+        let twice = [alice_accounts.clone(), alice_accounts.clone()].concat();
+        assert!(policy
+            .check_guardians(&[alice_key, alice_key], &twice)
+            .is_err());
+        assert!(policy
+            .check_guardians(&[alice_key, bob_key], &alice_accounts)
+            .is_err());
+        let swapped = [bob_accounts.clone(), alice_accounts.clone()].concat();
+        assert!(policy
+            .check_guardians(&[alice_key, bob_key], &swapped)
+            .is_err());
+        let mut not_signing = both_accounts.clone();
+        not_signing[3].is_signer = false;
+        assert_eq!(
+            policy
+                .check_guardians(&[alice_key, bob_key], &not_signing)
+                .unwrap_err(),
+            error!(ErrorCode::AccountNotSigner)
+        );
+
+        // only guardians of the policy
+        let with_eve = [alice_accounts, eve_accounts].concat();
+        assert_eq!(
+            policy
+                .check_guardians(&[alice_key, eve_key], &with_eve)
+                .unwrap_err(),
+            error!(DidSolError::InvalidGuardian)
+        );
+    }
+
+    #[test]
+    fn test_recovery_can_be_executed_after_the_challenge_period() {
+        let guardians = [TestGuardian::new(), TestGuardian::new()];
+        let mut policy = create_test_policy(&[&guardians[0], &guardians[1]], 1);
+        let recovery = create_test_recovery(&policy);
+        let executable_from = 1_000 + policy.challenge_period;
+        assert_eq!(
+            recovery.expires_at,
+            executable_from + RECOVERY_EXECUTION_PERIOD
+        );
+
+        // recovery not yet past the challenge period
+        assert_eq!(
+            recovery
+                .check_executable(&policy, executable_from - 1)
+                .unwrap_err(),
+            error!(DidSolError::RecoveryChallengePeriodActive)
+        );
+        recovery.check_executable(&policy, executable_from).unwrap();
+        assert_eq!(
+            recovery
+                .check_executable(&policy, recovery.expires_at)
+                .unwrap_err(),
+            error!(DidSolError::RecoveryExpired)
+        );
+
+        // the threshold of the current policy applies
+        policy.threshold = 2;
+        assert_eq!(
+            recovery
+                .check_executable(&policy, executable_from)
+                .unwrap_err(),
+            error!(DidSolError::RecoveryThresholdNotMet)
+        );
+        policy.guardians.remove(0);
+        policy.threshold = 1;
+        assert!(recovery.check_executable(&policy, executable_from).is_err());
+    }
+
+    #[test]
+    fn test_stale_recovery() {
+        let guardian = TestGuardian::new();
+        let policy = create_test_policy(&[&guardian], 1);
+        let recovery = create_test_recovery(&policy);
+        let mut did = DidAccount::new(255, &Pubkey::new_unique());
+        did.set_recovery_policy(Some(policy.clone())).unwrap();
+        did.check_recovery_verification_method(&recovery.verification_method)
+            .unwrap();
+
+        assert!(!recovery.is_stale(&did, 1_000));
+        assert!(recovery.is_stale(&did, recovery.expires_at));
+
+        // the fragment was added in the meantime
+        let mut added = did.clone();
+        added
+            .add_verification_method(VerificationMethod {
+                flags: VerificationMethodFlags::AUTHENTICATION.bits(),
+                ..recovery.verification_method.clone()
+            })
+            .unwrap();
+        assert!(recovery.is_stale(&added, 1_000));
+
+        // the recovery policy was removed
+        did.set_recovery_policy(None).unwrap();
+        assert!(recovery.is_stale(&did, 1_000));
+    }
+
+    #[test]
+    fn test_cancel_recovery_requires_did_authority() {
+        let authority = Pubkey::new_unique();
+        let bump = derive_did_account(&authority.to_bytes()).1;
+        let mut did = DidAccount::new(bump, &authority);
+
+        // cancel_recovery authorizes the update like any other instruction
+        let cancelled = did.authorize_update(
+            &Pubkey::new_unique(),
+            "cancel_recovery",
+            &Pubkey::new_unique().to_bytes(),
+            None,
+            None,
+            None,
+            InstructionPermissions::CANCEL_RECOVERY,
+            &[],
+        );
+        assert_eq!(
+            cancelled.err().unwrap(),
+            error!(DidSolError::WrongAuthorityForDid)
+        );
+    }
+}
@@ -1,5 +1,9 @@
 //! Data accounts for this program.
 
 mod did_account;
+mod did_account_view;
+mod did_recovery;
 
 pub use did_account::*;
+pub use did_account_view::*;
+pub use did_recovery::*;
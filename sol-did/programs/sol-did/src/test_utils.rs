@@ -0,0 +1,76 @@
+//! Helpers for unit tests.
+use crate::state::Secp256k1RawSignature;
+use anchor_lang::prelude::*;
+use solana_program::sysvar::instructions::{
+    construct_instructions_data, store_current_index, BorrowedInstruction,
+};
+
+/// A non-signing, read-only account for tests
+pub fn create_test_account_info<'a>(
+    key: &'a Pubkey,
+    owner: &'a Pubkey,
+    lamports: &'a mut u64,
+    data: &'a mut [u8],
+) -> AccountInfo<'a> {
+    AccountInfo::new(key, false, false, lamports, data, owner, false, 0)
+}
+
+/// Instruction data of an ed25519 or secp256r1 precompile verifying one (zeroed) signature
+pub fn create_precompile_instruction_data(
+    public_key: &[u8],
+    message: &[u8],
+    trailing_data: &[u8],
+) -> Vec<u8> {
+    let public_key_offset: u16 = 2 + 14;
+    let signature_offset = public_key_offset + public_key.len() as u16;
+    let message_offset = signature_offset + 64;
+
+    let mut data = vec![1, 0];
+    for offset in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(public_key);
+    data.extend_from_slice(&[0; 64]);
+    data.extend_from_slice(message);
+    data.extend_from_slice(trailing_data);
+    data
+}
+
+/// Instructions sysvar data of a precompile instruction followed by the current instruction
+pub fn create_instructions_sysvar_data(precompile_program_id: &Pubkey, data: &[u8]) -> Vec<u8> {
+    let mut sysvar_data = construct_instructions_data(&[
+        BorrowedInstruction {
+            program_id: precompile_program_id,
+            accounts: vec![],
+            data,
+        },
+        BorrowedInstruction {
+            program_id: &crate::ID,
+            accounts: vec![],
+            data: &[],
+        },
+    ]);
+    store_current_index(&mut sysvar_data, 1);
+    sysvar_data
+}
+
+/// Signs a 32 bytes hash with an Ethereum key
+pub fn eth_sign_hash(
+    secret_key: &libsecp256k1::SecretKey,
+    hash: &[u8; 32],
+) -> Secp256k1RawSignature {
+    let (signature, recovery_id) =
+        libsecp256k1::sign(&libsecp256k1::Message::parse(hash), secret_key);
+    Secp256k1RawSignature {
+        signature: signature.serialize(),
+        recovery_id: recovery_id.serialize(),
+    }
+}
@@ -1,11 +1,33 @@
-use crate::constants::{DID_PREFIX, DID_SOL_PREFIX};
+use crate::constants::{DID_PREFIX, DID_SOL_PREFIX, SIGNED_UPDATE_DOMAIN_V1};
+use crate::errors::DidSolError;
 use crate::{id, DID_ACCOUNT_SEED};
-use anchor_lang::prelude::{Error, ErrorCode};
+use anchor_lang::prelude::{
+    error, AccountInfo, CpiContext, Error, ErrorCode, Program, Signer, System, ToAccountInfo,
+};
+use anchor_lang::system_program;
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use solana_program::hash::{hash, HASH_BYTES};
+use solana_program::instruction::Instruction;
 use solana_program::keccak;
 use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
 use solana_program::secp256k1_recover::{
     secp256k1_recover, Secp256k1Pubkey, Secp256k1RecoverError,
 };
+use solana_program::sysvar;
+use solana_program::sysvar::Sysvar;
+
+const PRECOMPILE_SIGNATURE_OFFSETS_START: usize = 2;
+const PRECOMPILE_SIGNATURE_OFFSETS_SIZE: usize = 14;
+pub const ED25519_PUBKEY_SIZE: usize = 32;
+pub const SECP256R1_COMPRESSED_PUBKEY_SIZE: usize = 33;
+/// Key data of a passkey: the compressed P-256 key followed by the rpIdHash of its relying party
+pub const WEBAUTHN_KEY_DATA_SIZE: usize = SECP256R1_COMPRESSED_PUBKEY_SIZE + HASH_BYTES;
+/// rpIdHash (32 bytes), flags (1 byte) and signCount (4 bytes)
+const WEBAUTHN_AUTHENTICATOR_DATA_MIN_SIZE: usize = 37;
+const WEBAUTHN_FLAG_USER_PRESENT: u8 = 0x01;
+/// clientDataJSON type of an assertion, registrations (`webauthn.create`) are rejected
+const WEBAUTHN_TYPE_GET: &str = "webauthn.get";
 
 pub fn convert_secp256k1pub_key_to_address(pubkey: &Secp256k1Pubkey) -> [u8; 20] {
     let mut address = [0u8; 20];
@@ -27,6 +49,65 @@ pub fn check_other_controllers(controllers: &[String]) -> bool {
         .all(|did| is_did_prefix(did) && !is_did_sol_prefix(did))
 }
 
+/// Returns true if `uri` is an absolute URI (RFC 3986), i.e. it has a scheme followed by a
+/// non-empty part without whitespace, e.g. `did:web:example.com` or `https://w3id.org/security/v2`.
+pub fn is_valid_uri(uri: &str) -> bool {
+    let (scheme, rest) = match uri.split_once(':') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let mut scheme_chars = scheme.chars();
+    matches!(scheme_chars.next(), Some(first) if first.is_ascii_alphabetic())
+        && scheme_chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !rest.is_empty()
+        && !rest.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// Returns the Anchor discriminator of the instruction `name`.
+pub fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// Encodes `value` as a JSON string (including the quotes).
+pub fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Returns the version 1 message that detached signatures of an update sign (followed by the
+/// nonce). It binds `payload` to the program, the DID account and the instruction:
+/// SIGNED_UPDATE_DOMAIN_V1 || program_id || did_account || discriminator || payload
+pub fn signed_update_message(
+    program_id: &Pubkey,
+    did_account: &Pubkey,
+    instruction: &str,
+    payload: &[u8],
+) -> Vec<u8> {
+    [
+        SIGNED_UPDATE_DOMAIN_V1,
+        program_id.as_ref(),
+        did_account.as_ref(),
+        instruction_discriminator(instruction).as_ref(),
+        payload,
+    ]
+    .concat()
+}
+
 /// Returns the address that signed message producing signature.
 pub fn eth_verify_message(
     message: &[u8],
@@ -66,6 +147,116 @@ pub fn eth_verify_message(
     secp256k1_pubkey
 }
 
+/// Returns the key that signed the EIP-712 typed data with signing hash `typed_data_hash`.
+pub fn eth_verify_typed_data(
+    typed_data_hash: &[u8; 32],
+    signature: [u8; 64],
+    recovery_id: u8,
+) -> Result<Secp256k1Pubkey, Secp256k1RecoverError> {
+    secp256k1_recover(typed_data_hash, recovery_id, signature.as_ref())
+}
+
+/// A signature verified by a native signature verification program instruction.
+pub struct PrecompileSignature<'a> {
+    pub public_key: &'a [u8],
+    pub message: &'a [u8],
+    /// Instruction data following the message
+    pub trailing_data: &'a [u8],
+}
+
+/// Returns the signatures verified by a native Ed25519 or secp256r1 program instruction (both
+/// programs share the same instruction layout). Signatures whose public key or message live in
+/// another instruction are ignored.
+pub fn precompile_verified_signatures(
+    instruction_data: &[u8],
+    public_key_size: usize,
+) -> Vec<PrecompileSignature<'_>> {
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let bytes = instruction_data.get(offset..offset + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    };
+    let num_signatures = instruction_data.first().copied().unwrap_or(0) as usize;
+
+    (0..num_signatures)
+        .filter_map(|index| {
+            let start =
+                PRECOMPILE_SIGNATURE_OFFSETS_START + index * PRECOMPILE_SIGNATURE_OFFSETS_SIZE;
+            let public_key_offset = read_u16(start + 4)? as usize;
+            let public_key_instruction_index = read_u16(start + 6)?;
+            let message_data_offset = read_u16(start + 8)? as usize;
+            let message_data_size = read_u16(start + 10)? as usize;
+            let message_instruction_index = read_u16(start + 12)?;
+            if public_key_instruction_index != u16::MAX || message_instruction_index != u16::MAX {
+                return None;
+            }
+
+            let message_end = message_data_offset + message_data_size;
+            Some(PrecompileSignature {
+                public_key: instruction_data
+                    .get(public_key_offset..public_key_offset + public_key_size)?,
+                message: instruction_data.get(message_data_offset..message_end)?,
+                trailing_data: instruction_data.get(message_end..)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the instructions of `program_id` that precede the current instruction.
+pub fn preceding_instructions(
+    instructions_sysvar: &AccountInfo,
+    program_id: &Pubkey,
+) -> Vec<Instruction> {
+    let current_index = match sysvar::instructions::load_current_index_checked(instructions_sysvar)
+    {
+        Ok(current_index) => current_index as usize,
+        Err(_) => return vec![],
+    };
+
+    (0..current_index)
+        .filter_map(|index| {
+            sysvar::instructions::load_instruction_at_checked(index, instructions_sysvar).ok()
+        })
+        .filter(|instruction| instruction.program_id == *program_id)
+        .collect()
+}
+
+/// The rpIdHash of the authenticatorData in the data signed by a passkey.
+pub fn webauthn_rp_id_hash(signed_data: &[u8]) -> Option<&[u8]> {
+    if signed_data.len() < WEBAUTHN_AUTHENTICATOR_DATA_MIN_SIZE + HASH_BYTES {
+        return None;
+    }
+    Some(&signed_data[..HASH_BYTES])
+}
+
+/// Verifies a WebAuthn assertion of a passkey over `challenge` for the relying party `rp_id_hash`.
+/// `signed_data` is the data signed by the passkey, authenticatorData || sha256(clientDataJSON).
+pub fn webauthn_verify_assertion(
+    signed_data: &[u8],
+    client_data_json: &[u8],
+    challenge: &[u8],
+    rp_id_hash: &[u8],
+) -> bool {
+    if webauthn_rp_id_hash(signed_data) != Some(rp_id_hash) {
+        return false;
+    }
+    let (authenticator_data, client_data_hash) =
+        signed_data.split_at(signed_data.len() - HASH_BYTES);
+    if authenticator_data[HASH_BYTES] & WEBAUTHN_FLAG_USER_PRESENT == 0
+        || hash(client_data_json).as_ref() != client_data_hash
+    {
+        return false;
+    }
+
+    // WebAuthn clients serialize the type and challenge first, in this order, see
+    // https://www.w3.org/TR/webauthn-2/#clientdatajson-serialization
+    let expected_prefix = format!(
+        r#"{{"type":"{}","challenge":"{}""#,
+        WEBAUTHN_TYPE_GET,
+        base64::encode_config(challenge, base64::URL_SAFE_NO_PAD)
+    );
+    client_data_json.starts_with(expected_prefix.as_bytes())
+}
+
 pub fn derive_did_account(key: &[u8]) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[DID_ACCOUNT_SEED.as_bytes(), key], &id())
 }
@@ -74,3 +265,136 @@ pub fn derive_did_account_with_bump(key: &[u8], bump_seed: u8) -> Result<Pubkey,
     Pubkey::create_program_address(&[DID_ACCOUNT_SEED.as_bytes(), key, &[bump_seed]], &id())
         .map_err(|_| Error::from(ErrorCode::ConstraintSeeds))
 }
+
+/// Reallocates the DID account `did_info` to `required_size`, so that it fits the DID after an
+/// instruction. `rent_account` is the optional payer or destination of the instruction.
+/// - Growth is paid by the rent account and needs the system program, it fails with
+///   `MissingRentPayer` without them. The runtime limits the growth of an account to
+///   MAX_PERMITTED_DATA_INCREASE per instruction, so larger growth fails with
+///   `AccountReallocExceedsLimit`. A single instruction cannot add that much to a DID.
+/// - The rent freed by shrinking is refunded to the rent account. Without one, the account keeps
+///   its size.
+pub fn realloc_did_account<'info>(
+    did_info: &AccountInfo<'info>,
+    required_size: usize,
+    rent_account: Option<&Signer<'info>>,
+    system_program: Option<&Program<'info, System>>,
+) -> Result<(), Error> {
+    let current_size = did_info.data_len();
+
+    if required_size > current_size {
+        let (payer, system_program) = rent_account
+            .zip(system_program)
+            .ok_or_else(|| error!(DidSolError::MissingRentPayer))?;
+        if required_size - current_size > MAX_PERMITTED_DATA_INCREASE {
+            return Err(error!(ErrorCode::AccountReallocExceedsLimit));
+        }
+
+        let rent_minimum = Rent::get()?.minimum_balance(required_size);
+        if rent_minimum > did_info.lamports() {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: payer.to_account_info(),
+                        to: did_info.clone(),
+                    },
+                ),
+                rent_minimum - did_info.lamports(),
+            )?;
+        }
+        did_info.realloc(required_size, false)?;
+    } else if required_size < current_size {
+        if let Some(destination) = rent_account {
+            did_info.realloc(required_size, false)?;
+
+            let rent_minimum = Rent::get()?.minimum_balance(required_size);
+            let refund = did_info.lamports().saturating_sub(rent_minimum);
+            **did_info.try_borrow_mut_lamports()? -= refund;
+            **destination.try_borrow_mut_lamports()? += refund;
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts the `size` requested by `resize`, the account must still fit the serialized DID of
+/// `did_size` bytes.
+pub fn check_did_account_size(size: u32, did_size: usize) -> Result<usize, Error> {
+    let size = usize::try_from(size).map_err(|_| error!(DidSolError::ConversionError))?;
+    if size < 8 + did_size {
+        return Err(error!(DidSolError::InvalidAccountSize));
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::create_test_account_info;
+    use solana_program::bpf_loader;
+
+    #[test]
+    fn test_realloc_did_account_without_rent_account() {
+        let did_key = Pubkey::new_unique();
+        let payer_key = Pubkey::new_unique();
+        let (mut did_lamports, mut payer_lamports, mut system_program_lamports) = (1, 1, 1);
+        let (mut did_data, mut payer_data, mut system_program_data) =
+            (vec![0; 100], vec![], vec![]);
+        let did_info =
+            create_test_account_info(&did_key, &crate::ID, &mut did_lamports, &mut did_data);
+        let mut payer_info = create_test_account_info(
+            &payer_key,
+            &system_program::ID,
+            &mut payer_lamports,
+            &mut payer_data,
+        );
+        payer_info.is_signer = true;
+        payer_info.is_writable = true;
+        let payer = Signer::try_from(&payer_info).unwrap();
+        let mut system_program_info = create_test_account_info(
+            &system_program::ID,
+            &bpf_loader::ID,
+            &mut system_program_lamports,
+            &mut system_program_data,
+        );
+        system_program_info.executable = true;
+        let system_program = Program::<System>::try_from(&system_program_info).unwrap();
+
+        // growth needs a payer and the system program
+        assert_eq!(
+            realloc_did_account(&did_info, 101, None, Some(&system_program)).unwrap_err(),
+            error!(DidSolError::MissingRentPayer)
+        );
+        assert_eq!(
+            realloc_did_account(&did_info, 101, Some(&payer), None).unwrap_err(),
+            error!(DidSolError::MissingRentPayer)
+        );
+        assert_eq!(
+            realloc_did_account(
+                &did_info,
+                101 + MAX_PERMITTED_DATA_INCREASE,
+                Some(&payer),
+                Some(&system_program)
+            )
+            .unwrap_err(),
+            error!(ErrorCode::AccountReallocExceedsLimit)
+        );
+
+        // without a destination the account keeps its size
+        realloc_did_account(&did_info, 50, None, None).unwrap();
+        assert_eq!(did_info.data_len(), 100);
+    }
+
+    #[test]
+    fn test_check_did_account_size() {
+        let did_size = 100;
+        assert_eq!(
+            check_did_account_size(107, did_size).unwrap_err(),
+            error!(DidSolError::InvalidAccountSize)
+        );
+        assert_eq!(check_did_account_size(108, did_size).unwrap(), 108);
+        assert_eq!(check_did_account_size(20_000, did_size).unwrap(), 20_000);
+    }
+}
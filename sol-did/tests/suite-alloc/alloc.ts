@@ -296,7 +296,7 @@ describe('sol-did alloc operations', () => {
     return expect(
       service.resize(NEW_ACCOUNT_SIZE, authority.publicKey).rpc()
     ).to.be.rejectedWith(
-      'Error Code: AccountDidNotSerialize. Error Number: 3004. Error Message: Failed to serialize the account'
+      'Error Code: InvalidAccountSize. Error Number: 6031. Error Message: Invalid size. The DID account must be large enough for the serialized DID'
     );
   });
 });
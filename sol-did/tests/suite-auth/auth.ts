@@ -1,5 +1,5 @@
 import * as anchor from '@project-serum/anchor';
-import { LangErrorCode, Program } from '@project-serum/anchor';
+import { Program } from '@project-serum/anchor';
 import { SolDid } from '@identity.com/sol-did-idl';
 
 import chai, { expect } from 'chai';
@@ -62,7 +62,7 @@ describe('sol-did auth operations', () => {
         .withPartialSigners(nonAuthoritySigner)
         .rpc()
     ).to.be.rejectedWith(
-      'Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated'
+      'Error Code: WrongAuthorityForDid. Error Number: 6012. Error Message: Wrong Authority for given DID'
     );
   });
 
@@ -218,9 +218,7 @@ describe('sol-did auth operations', () => {
     return expect(
       programProvider.sendAndConfirm(transaction, [nonAuthoritySigner])
     ).to.be.rejectedWith(
-      `Error processing Instruction 0: custom program error: 0x${LangErrorCode.ConstraintRaw.toString(
-        16
-      )}`
+      'Error processing Instruction 0: custom program error: 0x177c'
     );
   });
 
@@ -250,9 +248,7 @@ describe('sol-did auth operations', () => {
     return expect(
       programProvider.sendAndConfirm(transaction, [nonAuthoritySigner])
     ).to.be.rejectedWith(
-      `Error processing Instruction 0: custom program error: 0x${LangErrorCode.ConstraintRaw.toString(
-        16
-      )}`
+      'Error processing Instruction 0: custom program error: 0x177c'
     );
   });
 
@@ -267,7 +263,7 @@ describe('sol-did auth operations', () => {
         .withPartialSigners(nonAuthoritySigner)
         .rpc()
     ).to.be.rejectedWith(
-      'Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated'
+      'Error Code: WrongAuthorityForDid. Error Number: 6012. Error Message: Wrong Authority for given DID'
     );
   });
 
@@ -315,9 +311,7 @@ describe('sol-did auth operations', () => {
     return expect(
       programProvider.sendAndConfirm(transaction, [nonAuthoritySigner])
     ).to.be.rejectedWith(
-      `Error processing Instruction 0: custom program error: 0x${LangErrorCode.ConstraintRaw.toString(
-        16
-      )}`
+      'Error processing Instruction 0: custom program error: 0x177c'
     );
   });
 
@@ -346,9 +340,7 @@ describe('sol-did auth operations', () => {
     return expect(
       programProvider.sendAndConfirm(transaction, [nonAuthoritySigner])
     ).to.be.rejectedWith(
-      `Error processing Instruction 0: custom program error: 0x${LangErrorCode.ConstraintRaw.toString(
-        16
-      )}`
+      'Error processing Instruction 0: custom program error: 0x177c'
     );
   });
 
@@ -392,7 +384,7 @@ describe('sol-did auth operations', () => {
     return expect(
       service.setVerificationMethodFlags(newEthKeyAlias, newFlags).rpc()
     ).to.be.rejectedWith(
-      'Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated.'
+      'Error Code: WrongAuthorityForDid. Error Number: 6012. Error Message: Wrong Authority for given DID.'
     );
   });
 
@@ -404,7 +396,7 @@ describe('sol-did auth operations', () => {
     return expect(
       service.setVerificationMethodFlags(newEthKeyAlias, newFlags).rpc()
     ).to.be.rejectedWith(
-      'Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated.'
+      'Error Code: WrongAuthorityForDid. Error Number: 6012. Error Message: Wrong Authority for given DID.'
     );
   });
 